@@ -0,0 +1,399 @@
+//! RFC 4180 CSV reader and writer
+//!
+//! Supports quoted fields (`"a, b"`), escaped quotes (`""`), line breaks inside quoted fields,
+//! CRLF line endings, configurable delimiter and files with or without a header line.
+//! Errors carry 1-based line and column numbers of the offending character.
+//!
+//! Example:
+//! ```
+//! use ft_linear_regression::csv::{parse, CsvOptions};
+//!
+//! let table = parse("\"name\",\"value\"\r\n\"a, b\",\"say \"\"hi\"\"\"\r\n", &CsvOptions::default()).unwrap();
+//! assert_eq!(table.header, vec!["name", "value"]);
+//! assert_eq!(table.records[0].get(0).unwrap().value, "a, b");
+//! assert_eq!(table.records[0].get(1).unwrap().value, "say \"hi\"");
+//! ```
+use std::fs;
+use std::io::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::result::{Result, TrainError};
+
+/// Reader configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+	/// Field delimiter
+	///
+	/// By default this is set to `,`.
+	/// Common alternatives are `\t` (TSV) and `;`.
+	pub delimiter: char,
+	/// Whether the first record holds column labels
+	///
+	/// By default this is set to `true`.
+	/// Without header columns are labeled `column_0`, `column_1`, ...
+	pub has_header: bool,
+}
+
+impl Default for CsvOptions {
+	fn default() -> Self {
+		Self {
+			delimiter: ',',
+			has_header: true,
+		}
+	}
+}
+
+/// Parses a delimiter given as a single character, `tab` or `\t`
+///
+/// Quotes and line breaks can't delimit fields.
+pub fn parse_delimiter(s: &str) -> std::result::Result<char, String> {
+	let mut chars = s.chars();
+	match s {
+		"tab" | "\\t" => Ok('\t'),
+		_ => match (chars.next(), chars.next()) {
+			(Some(c), None) if !matches!(c, '"' | '\n' | '\r') => Ok(c),
+			_ => Err(format!(
+				"Invalid delimiter {:?}, expected single character or \"tab\"",
+				s
+			)),
+		},
+	}
+}
+
+/// Single field with its position in the file, quotes already removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+	pub value: String,
+	/// 1-based line the field starts on
+	pub line: usize,
+	/// 1-based column the field starts on
+	pub column: usize,
+}
+
+/// Single data record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+	pub fields: Vec<Field>,
+}
+
+impl Record {
+	/// Line the record starts on
+	pub fn line(&self) -> usize {
+		self.fields[0].line
+	}
+
+	/// Field getter
+	pub fn get(&self, i: usize) -> Option<&Field> {
+		self.fields.get(i)
+	}
+
+	/// Number of fields
+	pub fn len(&self) -> usize {
+		self.fields.len()
+	}
+
+	/// Whether the record has no fields
+	pub fn is_empty(&self) -> bool {
+		self.fields.is_empty()
+	}
+}
+
+/// Parsed file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvTable {
+	/// Column labels, generated if the file has no header
+	pub header: Vec<String>,
+	/// Data records, all of the same length as `header`
+	pub records: Vec<Record>,
+}
+
+/// Reads and parses given file
+pub fn read(filename: &str, options: &CsvOptions) -> Result<CsvTable> {
+	parse(&fs::read_to_string(filename)?, options)
+}
+
+/// Parses CSV content
+///
+/// Blank lines are skipped. Every record has to have the same number of fields.
+pub fn parse(contents: &str, options: &CsvOptions) -> Result<CsvTable> {
+	let mut records = Parser::new(contents, options.delimiter).records()?;
+	let header: Vec<String> = match options.has_header && !records.is_empty() {
+		true => records
+			.remove(0)
+			.fields
+			.into_iter()
+			.map(|f| f.value.trim().to_string())
+			.collect(),
+		false => (0..records.first().map_or(0, |r| r.len()))
+			.map(|i| format!("column_{}", i))
+			.collect(),
+	};
+	for record in records.iter() {
+		if record.len() != header.len() {
+			// Point to the first extra field or to the last one if some are missing
+			let field = &record.fields[record.len().min(header.len() + 1) - 1];
+			return Err(TrainError::Csv {
+				line: field.line,
+				column: field.column,
+				message: format!("expected {} fields, found {}", header.len(), record.len()),
+			});
+		}
+	}
+	Ok(CsvTable { header, records })
+}
+
+/// Writes a single record terminated by CRLF, quoting fields when needed
+pub fn write_record<W: Write, S: AsRef<str>>(
+	out: &mut W,
+	fields: &[S],
+	delimiter: char,
+) -> io::Result<()> {
+	let quoted: Vec<String> = fields
+		.iter()
+		.map(|field| {
+			let field = field.as_ref();
+			match field.contains([delimiter, '"', '\r', '\n']) {
+				true => format!("\"{}\"", field.replace('"', "\"\"")),
+				false => field.to_string(),
+			}
+		})
+		.collect();
+	write!(out, "{}\r\n", quoted.join(&delimiter.to_string()))
+}
+
+/// Parser state within a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+	/// Nothing of the field was read yet
+	Start,
+	/// Inside unquoted field
+	Unquoted,
+	/// Inside quoted field
+	Quoted,
+	/// Quote read inside quoted field, either escape or end of the field
+	Quote,
+}
+
+/// Character level parser keeping track of the position
+struct Parser<'a> {
+	chars: Peekable<Chars<'a>>,
+	delimiter: char,
+	line: usize,
+	column: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn new(contents: &'a str, delimiter: char) -> Self {
+		Self {
+			chars: contents.chars().peekable(),
+			delimiter,
+			line: 1,
+			column: 1,
+		}
+	}
+
+	/// Next character with its position. CRLF and lone CR are returned as `\n`.
+	fn next(&mut self) -> Option<(char, usize, usize)> {
+		let mut c = self.chars.next()?;
+		let position = (self.line, self.column);
+		if c == '\r' {
+			if self.chars.peek() == Some(&'\n') {
+				self.chars.next();
+			}
+			c = '\n';
+		}
+		if c == '\n' {
+			self.line += 1;
+			self.column = 1;
+		} else {
+			self.column += 1;
+		}
+		Some((c, position.0, position.1))
+	}
+
+	fn error(line: usize, column: usize, message: &str) -> TrainError {
+		TrainError::Csv {
+			line,
+			column,
+			message: message.to_string(),
+		}
+	}
+
+	fn records(&mut self) -> Result<Vec<Record>> {
+		let mut records: Vec<Record> = Vec::new();
+		let mut fields: Vec<Field> = Vec::new();
+		let mut value = String::new();
+		let mut state = State::Start;
+		// Start of the current field and whether it was quoted
+		let mut start = (1, 1);
+		let mut quoted = false;
+		while let Some((c, line, column)) = self.next() {
+			match (state, c) {
+				(State::Quoted, '"') => state = State::Quote,
+				(State::Quoted, _) => value.push(c),
+				(State::Quote, '"') => {
+					value.push('"');
+					state = State::Quoted;
+				}
+				(State::Unquoted, '"') => {
+					return Err(Self::error(
+						line,
+						column,
+						"unexpected quote in unquoted field",
+					))
+				}
+				(State::Start, '"') => {
+					start = (line, column);
+					quoted = true;
+					state = State::Quoted;
+				}
+				(_, '\n') => {
+					if state == State::Start {
+						start = (line, column);
+					}
+					fields.push(Field {
+						value: std::mem::take(&mut value),
+						line: start.0,
+						column: start.1,
+					});
+					// Skip blank lines
+					if fields.len() > 1 || quoted || !fields[0].value.is_empty() {
+						records.push(Record {
+							fields: std::mem::take(&mut fields),
+						});
+					}
+					fields.clear();
+					quoted = false;
+					state = State::Start;
+				}
+				(_, d) if d == self.delimiter => {
+					if state == State::Start {
+						start = (line, column);
+					}
+					fields.push(Field {
+						value: std::mem::take(&mut value),
+						line: start.0,
+						column: start.1,
+					});
+					quoted = false;
+					state = State::Start;
+				}
+				(State::Quote, _) => {
+					return Err(Self::error(
+						line,
+						column,
+						"unexpected character after closing quote",
+					))
+				}
+				(State::Start, _) => {
+					start = (line, column);
+					value.push(c);
+					state = State::Unquoted;
+				}
+				(State::Unquoted, _) => value.push(c),
+			}
+		}
+		match state {
+			State::Quoted => Err(Self::error(start.0, start.1, "unterminated quoted field")),
+			// Content without trailing line break
+			_ if state != State::Start || !fields.is_empty() => {
+				if state == State::Start {
+					start = (self.line, self.column);
+				}
+				fields.push(Field {
+					value,
+					line: start.0,
+					column: start.1,
+				});
+				records.push(Record { fields });
+				Ok(records)
+			}
+			_ => Ok(records),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn values(table: &CsvTable) -> Vec<Vec<&str>> {
+		table
+			.records
+			.iter()
+			.map(|r| r.fields.iter().map(|f| f.value.as_str()).collect())
+			.collect()
+	}
+
+	#[test]
+	fn quoted_fields() {
+		let table = parse(
+			"\"carat\",\"price\"\n\"1,5\",\"a \"\"b\"\"\"\n\"multi\nline\",\n",
+			&CsvOptions::default(),
+		)
+		.unwrap();
+		assert_eq!(table.header, vec!["carat", "price"]);
+		assert_eq!(
+			values(&table),
+			vec![vec!["1,5", "a \"b\""], vec!["multi\nline", ""]]
+		);
+	}
+
+	#[test]
+	fn write_roundtrip() {
+		let rows = [
+			vec!["a;b", "plain", ""],
+			vec!["say \"hi\"", "multi\nline", "1.5"],
+		];
+		let mut out = Vec::new();
+		for row in rows.iter() {
+			write_record(&mut out, row, ';').unwrap();
+		}
+		let text = String::from_utf8(out).unwrap();
+		assert!(text.starts_with("\"a;b\";plain;\r\n"));
+		let options = CsvOptions {
+			delimiter: ';',
+			has_header: false,
+		};
+		let table = parse(&text, &options).unwrap();
+		assert_eq!(values(&table), rows.to_vec());
+	}
+
+	#[test]
+	fn crlf_and_blank_lines() {
+		let table = parse("a,b\r\n1,2\r\n\r\n3,4", &CsvOptions::default()).unwrap();
+		assert_eq!(values(&table), vec![vec!["1", "2"], vec!["3", "4"]]);
+		assert_eq!(table.records[1].line(), 4);
+		assert_eq!(table.records[1].get(1).unwrap().column, 3);
+	}
+
+	#[test]
+	fn delimiter_and_no_header() {
+		let options = CsvOptions {
+			delimiter: '\t',
+			has_header: false,
+		};
+		let table = parse("1\t2,5\n3\t4\n", &options).unwrap();
+		assert_eq!(table.header, vec!["column_0", "column_1"]);
+		assert_eq!(values(&table), vec![vec!["1", "2,5"], vec!["3", "4"]]);
+		assert_eq!(parse_delimiter("tab"), Ok('\t'));
+		assert_eq!(parse_delimiter(";"), Ok(';'));
+		for invalid in ["", ";;", "\"", "\n"] {
+			assert!(parse_delimiter(invalid).is_err(), "{:?}", invalid);
+		}
+	}
+
+	#[test]
+	fn error_positions() {
+		let position = |contents: &str| match parse(contents, &CsvOptions::default()) {
+			Err(TrainError::Csv { line, column, .. }) => (line, column),
+			other => panic!("Expected CSV error, got {:?}", other),
+		};
+		assert_eq!(position("a,b\n1,2\"\n"), (2, 4));
+		assert_eq!(position("a,b\n\"1\"x,2\n"), (2, 4));
+		assert_eq!(position("a,b\n1,\"2\n"), (2, 3));
+		assert_eq!(position("a,b\n1,2,3\n"), (2, 5));
+		assert_eq!(position("a,b\n1\n"), (2, 1));
+	}
+}
@@ -0,0 +1,466 @@
+//! Dataset loading and handling
+//!
+//! The dataset is a set of samples, where features are the input of
+//! the linear regression (e.g. mileage) and target is the estimated variable (e.g. price).
+use std::fmt;
+use std::str::FromStr;
+
+use rand::prelude::*;
+
+use crate::csv::{self, CsvOptions};
+use crate::missing::{self, InvalidCounts, LoadReport, MissingPolicy};
+use crate::params::Spec;
+use crate::result::{Result, TrainError};
+
+/// Column selector, either by header name or by zero-based index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+	Index(usize),
+	Name(String),
+}
+
+impl Column {
+	/// Finds position of the column in given header.
+	///
+	/// Returns [`TrainError::UnknownColumn`] if there is no such column.
+	pub fn resolve(&self, header: &[&str]) -> Result<usize> {
+		let position = match self {
+			Column::Index(i) => Some(*i).filter(|i| *i < header.len()),
+			Column::Name(name) => header.iter().position(|h| h == name),
+		};
+		position.ok_or_else(|| TrainError::UnknownColumn {
+			column: self.to_string(),
+			available: header.iter().map(|h| h.to_string()).collect(),
+		})
+	}
+}
+
+/// Parses index if the whole string is a number, otherwise it is a column name
+impl From<&str> for Column {
+	fn from(s: &str) -> Self {
+		match s.trim().parse::<usize>() {
+			Ok(i) => Column::Index(i),
+			Err(_) => Column::Name(s.trim().to_string()),
+		}
+	}
+}
+
+impl From<usize> for Column {
+	fn from(i: usize) -> Self {
+		Column::Index(i)
+	}
+}
+
+impl fmt::Display for Column {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Column::Index(i) => write!(f, "#{}", i),
+			Column::Name(name) => write!(f, "\"{}\"", name),
+		}
+	}
+}
+
+/// Options for loading the dataset from a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadOptions {
+	/// Feature columns
+	///
+	/// By default this is the first column.
+	pub features: Vec<Column>,
+	/// Target column
+	///
+	/// By default this is the second column.
+	pub target: Column,
+	/// CSV reader configuration
+	pub csv: CsvOptions,
+	/// Handling of missing and invalid cells
+	///
+	/// By default loading fails on the first invalid cell.
+	pub missing: MissingPolicy,
+	/// Column of the sample weights
+	///
+	/// By default this is `Option::None`, all samples weigh 1.
+	pub weight: Option<Column>,
+}
+
+impl Default for LoadOptions {
+	fn default() -> Self {
+		Self {
+			features: vec![Column::Index(0)],
+			target: Column::Index(1),
+			csv: CsvOptions::default(),
+			missing: MissingPolicy::default(),
+			weight: Option::None,
+		}
+	}
+}
+
+/// Single row of the dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+	/// Feature values, in order of `Dataset::feature_labels`
+	pub features: Vec<f64>,
+	/// Target value
+	pub target: f64,
+	/// Weight of the sample in the fit and the metrics, at least 0
+	pub weight: f64,
+}
+
+impl Sample {
+	/// Default constructor, the sample weighs 1
+	pub fn new(features: Vec<f64>, target: f64) -> Self {
+		Self::weighted(features, target, 1.0)
+	}
+
+	/// Constructor of a sample with given weight
+	pub fn weighted(features: Vec<f64>, target: f64, weight: f64) -> Self {
+		Self {
+			features,
+			target,
+			weight,
+		}
+	}
+}
+
+/// Method of the train and test split
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Split {
+	/// Shuffled samples cut at the ratio
+	#[default]
+	Random,
+	/// Random within equal frequency bins of the target, so both sets cover its whole range
+	Stratified { bins: usize },
+	/// Last samples are the test set, for time-ordered data
+	Chronological,
+}
+
+/// Accepts `random`, `stratified:bins=5` and `chronological`, bins default to 5
+impl FromStr for Split {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let spec = Spec::parse(s)?;
+		let split = match spec.name {
+			"random" => Split::Random,
+			"stratified" => match spec.get("bins", 5.0) {
+				bins if bins >= 1.0 => Split::Stratified {
+					bins: bins as usize,
+				},
+				_ => return Err(String::from("Stratified split needs at least 1 bin")),
+			},
+			"chronological" => Split::Chronological,
+			_ => return Err(format!("Unknown split {:?}", spec.name)),
+		};
+		spec.finish(split)
+	}
+}
+
+impl fmt::Display for Split {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Split::Random => write!(f, "random"),
+			Split::Stratified { bins } => write!(f, "stratified:bins={}", bins),
+			Split::Chronological => write!(f, "chronological"),
+		}
+	}
+}
+
+/// Samples loaded from a data file
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+	/// Labels of the feature columns
+	pub feature_labels: Vec<String>,
+	/// Label of the target column
+	pub target_label: String,
+	/// Label of the weight column, `Option::None` if unweighted
+	pub weight_label: Option<String>,
+	/// Samples of data for linear regression
+	pub samples: Vec<Sample>,
+}
+
+impl Dataset {
+	/// Default constructor
+	pub fn new(feature_labels: Vec<String>, target_label: String, samples: Vec<Sample>) -> Self {
+		Self {
+			feature_labels,
+			target_label,
+			weight_label: Option::None,
+			samples,
+		}
+	}
+
+	/// Function that loads the `Dataset` struct from given CSV file.
+	///
+	/// Assumptions: First column is the only feature, second column is the target.
+	/// See [`Dataset::load_with`].
+	///
+	pub fn load(filename: &str) -> Result<Self> {
+		Self::load_with(filename, &LoadOptions::default())
+	}
+
+	/// Function that loads the `Dataset` struct from given CSV file, using given
+	/// columns as features and target.
+	///
+	/// See [`Dataset::load_with`].
+	///
+	pub fn load_columns(filename: &str, features: &[Column], target: &Column) -> Result<Self> {
+		let options = LoadOptions {
+			features: features.to_vec(),
+			target: target.clone(),
+			..LoadOptions::default()
+		};
+		Self::load_with(filename, &options)
+	}
+
+	/// Function that loads the `Dataset` struct from given CSV file.
+	///
+	/// See [`Dataset::load_with_report`].
+	///
+	pub fn load_with(filename: &str, options: &LoadOptions) -> Result<Self> {
+		Ok(Self::load_with_report(filename, options)?.0)
+	}
+
+	/// Function that loads the `Dataset` struct from given CSV file.
+	///
+	/// Each selected cell is parsed to finite f64. Invalid cells are handled
+	/// according to `LoadOptions::missing`, with [`MissingPolicy::Fail`] the first one
+	/// is reported with its line and column as [`TrainError::InvalidValue`].
+	/// Invalid weights are treated as invalid targets, negative weights fail with
+	/// [`TrainError::NegativeWeight`] and rows of zero weight are left out.
+	/// Returns the dataset and summary of dropped and imputed values.
+	///
+	pub fn load_with_report(filename: &str, options: &LoadOptions) -> Result<(Self, LoadReport)> {
+		let table = csv::read(filename, &options.csv)?;
+		let header: Vec<&str> = table.header.iter().map(|h| h.as_str()).collect();
+		let columns: Vec<usize> = options
+			.features
+			.iter()
+			.map(|c| c.resolve(&header))
+			.collect::<Result<_>>()?;
+		let target_column = options.target.resolve(&header)?;
+		let weight_column = match &options.weight {
+			Some(column) => Some(column.resolve(&header)?),
+			None => None,
+		};
+		let mut dataset = Self::new(
+			columns.iter().map(|i| header[*i].to_string()).collect(),
+			header[target_column].to_string(),
+			Vec::with_capacity(table.records.len()),
+		);
+		dataset.weight_label = weight_column.map(|i| header[i].to_string());
+		let mut report = LoadReport {
+			rows: table.records.len(),
+			..LoadReport::default()
+		};
+		// Parsed rows, invalid feature cells are kept for imputation
+		let mut rows = Vec::with_capacity(table.records.len());
+		for record in table.records.iter() {
+			let cell = |i: usize| missing::parse_field(&record.fields[i]);
+			let x: Vec<_> = columns.iter().map(|i| cell(*i)).collect();
+			let y = cell(target_column);
+			let w = weight_column.map_or(Ok(1.0), cell);
+			// Imputed rows are dropped only for invalid target or weight
+			let invalid = match options.missing {
+				MissingPolicy::Impute(_) => y.err().or(w.err()),
+				_ => x.iter().chain([&y, &w]).find_map(|c| c.err()),
+			};
+			match (options.missing, invalid) {
+				(_, None) => {}
+				(MissingPolicy::Fail, Some(invalid)) => return Err(missing::to_error(invalid)),
+				(_, Some((reason, _))) => {
+					report.dropped.add(reason);
+					continue;
+				}
+			}
+			let weight = w.unwrap();
+			if weight < 0.0 {
+				let field = &record.fields[weight_column.unwrap_or_default()];
+				return Err(TrainError::NegativeWeight {
+					line: field.line,
+					column: field.column,
+					value: weight,
+				});
+			}
+			if weight == 0.0 {
+				report.zero_weight += 1;
+				continue;
+			}
+			rows.push((x, y.unwrap(), weight));
+		}
+		if let MissingPolicy::Impute(imputation) = options.missing {
+			for j in 0..columns.len() {
+				let mut valid: Vec<f64> = rows.iter().filter_map(|(x, _, _)| x[j].ok()).collect();
+				if valid.len() == rows.len() {
+					continue;
+				}
+				let value = missing::impute_value(imputation, &mut valid)?;
+				let mut counts = InvalidCounts::default();
+				for (x, _, _) in rows.iter_mut() {
+					if let Err((reason, _)) = x[j] {
+						counts.add(reason);
+						x[j] = Ok(value);
+					}
+				}
+				report.imputed.missing += counts.missing;
+				report.imputed.non_numeric += counts.non_numeric;
+				report.imputed.non_finite += counts.non_finite;
+			}
+		}
+		dataset.samples = rows
+			.into_iter()
+			.map(|(x, y, w)| Sample::weighted(x.into_iter().map(|c| c.unwrap()).collect(), y, w))
+			.collect();
+		Ok((dataset, report))
+	}
+
+	/// Number of samples
+	pub fn len(&self) -> usize {
+		self.samples.len()
+	}
+
+	/// Whether the dataset holds no data
+	pub fn is_empty(&self) -> bool {
+		self.samples.is_empty()
+	}
+
+	/// Number of features
+	pub fn feature_count(&self) -> usize {
+		self.feature_labels.len()
+	}
+
+	/// Creates an empty dataset with the same labels
+	pub fn empty_like(&self) -> Self {
+		Self {
+			weight_label: self.weight_label.clone(),
+			..Self::new(
+				self.feature_labels.clone(),
+				self.target_label.clone(),
+				Vec::new(),
+			)
+		}
+	}
+
+	/// Whether the samples have weights of a column
+	pub fn is_weighted(&self) -> bool {
+		self.weight_label.is_some()
+	}
+
+	/// Splits the dataset into train and test sets at random. Returns `(train_set, test_set)`.
+	///
+	/// `training_distribution` is the ratio of the dataset to be used for training.
+	/// Same seed always produces the same split.
+	///
+	pub fn split(&self, training_distribution: f32, seed: u64) -> (Self, Self) {
+		self.split_with(Split::Random, training_distribution, seed)
+	}
+
+	/// Splits the dataset into train and test sets by given method. Returns `(train_set, test_set)`.
+	///
+	/// The test set has exactly `len * (1 - training_distribution)` samples, rounded down.
+	/// Both sets keep the order of the dataset.
+	pub fn split_with(&self, method: Split, training_distribution: f32, seed: u64) -> (Self, Self) {
+		let n = self.len();
+		let ratio = training_distribution.clamp(0.0, 1.0) as f64;
+		// Tolerance for the rounding of the ratio, e.g. 0.8 is stored as 0.800000011920929
+		let test_count = ((n as f64 * (1.0 - ratio + 1e-7)) as usize).min(n);
+		let mut is_test = vec![false; n];
+		let mut order: Vec<usize> = (0..n).collect();
+		match method {
+			Split::Random => {
+				order.shuffle(&mut StdRng::seed_from_u64(seed));
+				for i in order.iter().take(test_count) {
+					is_test[*i] = true;
+				}
+			}
+			Split::Stratified { bins } => {
+				// Equal frequency bins of the target
+				let mut bin = vec![0; n];
+				let mut by_target = order.clone();
+				by_target
+					.sort_by(|a, b| self.samples[*a].target.total_cmp(&self.samples[*b].target));
+				for (rank, i) in by_target.iter().enumerate() {
+					bin[*i] = rank * bins.max(1) / n;
+				}
+				// Every test_count / n-th sample of the shuffled bins, so each bin gets its share
+				order.shuffle(&mut StdRng::seed_from_u64(seed));
+				order.sort_by_key(|i| bin[*i]);
+				for (pos, i) in order.iter().enumerate() {
+					is_test[*i] = (pos + 1) * test_count / n > pos * test_count / n;
+				}
+			}
+			Split::Chronological => {
+				for flag in is_test[n - test_count..].iter_mut() {
+					*flag = true;
+				}
+			}
+		}
+		let mut train_set = self.empty_like();
+		let mut test_set = self.empty_like();
+		for (sample, test) in self.samples.iter().zip(is_test) {
+			match test {
+				true => test_set.samples.push(sample.clone()),
+				false => train_set.samples.push(sample.clone()),
+			}
+		}
+		(train_set, test_set)
+	}
+
+	/// Partitions the dataset into `k` folds of nearly equal size. Returns `(train_set, test_set)`
+	/// for every fold, the test set being the fold and the train set the rest.
+	///
+	/// Samples are shuffled by the seed, or kept in order for `Option::None`.
+	pub fn folds(&self, k: usize, seed: Option<u64>) -> Vec<(Self, Self)> {
+		let mut order: Vec<usize> = (0..self.len()).collect();
+		if let Some(seed) = seed {
+			order.shuffle(&mut StdRng::seed_from_u64(seed));
+		}
+		(0..k)
+			.map(|fold| {
+				let mut train_set = self.empty_like();
+				let mut test_set = self.empty_like();
+				for (i, index) in order.iter().enumerate() {
+					match i % k == fold {
+						true => test_set.samples.push(self.samples[*index].clone()),
+						false => train_set.samples.push(self.samples[*index].clone()),
+					}
+				}
+				(train_set, test_set)
+			})
+			.collect()
+	}
+
+	/// Returns `(min, max)` of each feature for normalisation.
+	pub fn feature_extremes(&self) -> Vec<(f64, f64)> {
+		let init = vec![(f64::MAX, f64::MIN); self.feature_count()];
+		self.samples.iter().fold(init, |mut acc, sample| {
+			for (extremes, x) in acc.iter_mut().zip(sample.features.iter()) {
+				*extremes = (extremes.0.min(*x), extremes.1.max(*x));
+			}
+			acc
+		})
+	}
+
+	/// FNV-1a hash of the labels and values, identifies the training data of a model
+	///
+	/// Weights are part of the hash for weighted datasets only.
+	pub fn fingerprint(&self) -> u64 {
+		let labels = self
+			.feature_labels
+			.iter()
+			.chain(std::iter::once(&self.target_label))
+			.chain(self.weight_label.iter())
+			.flat_map(|label| label.bytes().chain(std::iter::once(0)));
+		let weighted = self.is_weighted();
+		let values = self.samples.iter().flat_map(|sample| {
+			sample
+				.features
+				.iter()
+				.chain(std::iter::once(&sample.target))
+				.chain(Some(&sample.weight).filter(|_| weighted))
+				.flat_map(|x| x.to_bits().to_le_bytes())
+		});
+		labels.chain(values).fold(0xcbf29ce484222325, |hash, byte| {
+			(hash ^ byte as u64).wrapping_mul(0x100000001b3)
+		})
+	}
+}
@@ -0,0 +1,148 @@
+//! Range of the keys the model was trained on
+//!
+//! Linear model happily extrapolates, e.g. a negative mileage gives a price above
+//! the intercept. The [`Domain`] is stored with the model so the predictor can
+//! detect such keys and handle them per [`Extrapolation`] policy.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::metrics::quantile;
+
+/// Quantiles recorded for every feature
+pub const QUANTILES: [f64; 5] = [0.01, 0.25, 0.5, 0.75, 0.99];
+
+/// Handling of keys outside the training domain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Extrapolation {
+	/// Predict anyway and report the keys
+	#[default]
+	Warn,
+	/// Don't predict
+	Refuse,
+	/// Predict for the keys clamped into the domain
+	Clamp,
+}
+
+/// Accepts `warn`, `refuse` and `clamp`
+impl FromStr for Extrapolation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"warn" => Ok(Extrapolation::Warn),
+			"refuse" => Ok(Extrapolation::Refuse),
+			"clamp" => Ok(Extrapolation::Clamp),
+			_ => Err(format!("Unknown extrapolation policy {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Extrapolation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Extrapolation::Warn => write!(f, "warn"),
+			Extrapolation::Refuse => write!(f, "refuse"),
+			Extrapolation::Clamp => write!(f, "clamp"),
+		}
+	}
+}
+
+/// Training range of a single feature
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureRange {
+	pub min: f64,
+	pub max: f64,
+	/// Quantile and its value, see [`QUANTILES`]
+	pub quantiles: Vec<(f64, f64)>,
+}
+
+impl FeatureRange {
+	/// Fits the range on the values of a column
+	pub fn fit(values: &[f64]) -> Self {
+		let mut sorted = values.to_vec();
+		sorted.sort_by(|a, b| a.total_cmp(b));
+		Self {
+			min: sorted.first().cloned().unwrap_or(f64::NAN),
+			max: sorted.last().cloned().unwrap_or(f64::NAN),
+			quantiles: QUANTILES
+				.iter()
+				.map(|q| (*q, quantile(&sorted, *q)))
+				.collect(),
+		}
+	}
+
+	/// Whether the value is within `[min, max]`
+	pub fn contains(&self, x: f64) -> bool {
+		self.min <= x && x <= self.max
+	}
+}
+
+/// Training ranges of all features
+#[derive(Debug, Clone, PartialEq)]
+pub struct Domain {
+	pub features: Vec<FeatureRange>,
+}
+
+impl Domain {
+	/// Fits the ranges on the samples
+	pub fn fit(samples: &[Sample]) -> Self {
+		let feature_count = samples.first().map_or(0, |s| s.features.len());
+		Self {
+			features: (0..feature_count)
+				.map(|j| {
+					let column: Vec<f64> = samples.iter().map(|s| s.features[j]).collect();
+					FeatureRange::fit(&column)
+				})
+				.collect(),
+		}
+	}
+
+	/// Indices of the keys outside their training range
+	pub fn outside(&self, features: &[f64]) -> Vec<usize> {
+		features
+			.iter()
+			.zip(self.features.iter())
+			.enumerate()
+			.filter(|(_, (x, range))| !range.contains(**x))
+			.map(|(j, _)| j)
+			.collect()
+	}
+
+	/// Keys clamped into their training range
+	pub fn clamp(&self, features: &[f64]) -> Vec<f64> {
+		features
+			.iter()
+			.zip(self.features.iter())
+			.map(|(x, range)| x.clamp(range.min, range.max))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn ranges() {
+		let samples: Vec<Sample> = (0..=100)
+			.map(|i| Sample::new(vec![i as f64, -(i as f64) / 10.0], 0.0))
+			.collect();
+		let domain = Domain::fit(&samples);
+		assert_eq!(
+			(domain.features[0].min, domain.features[0].max),
+			(0.0, 100.0)
+		);
+		assert_eq!(
+			(domain.features[1].min, domain.features[1].max),
+			(-10.0, 0.0)
+		);
+		assert_eq!(domain.features[0].quantiles[0], (0.01, 1.0));
+		assert_eq!(domain.features[0].quantiles[2], (0.5, 50.0));
+		assert_eq!(domain.outside(&[50.0, -5.0]), Vec::<usize>::new());
+		assert_eq!(domain.outside(&[-1.0, 0.5]), vec![0, 1]);
+		assert_eq!(domain.outside(&[100.0, f64::NAN]), vec![1]);
+		assert_eq!(domain.clamp(&[-1.0, 0.5]), vec![0.0, 0.0]);
+		assert_eq!(domain.clamp(&[150.0, -3.0]), vec![100.0, -3.0]);
+	}
+}
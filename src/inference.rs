@@ -0,0 +1,283 @@
+//! Confidence and prediction intervals of the ordinary least squares fit
+//!
+//! With the design matrix `X` of the `n` training samples, `p` coefficients and the
+//! residual standard error `s = sqrt(SSE / (n - p))`, the standard errors at the key
+//! `x0 = [1, x_0, ..., x_n-1]` are:
+//! ```text
+//! mean response:   s * sqrt(x0^T * (X^T * X)^-1 * x0)
+//! new observation: s * sqrt(1 + x0^T * (X^T * X)^-1 * x0)
+//! ```
+//! and the interval is the estimate plus and minus the standard error times the quantile
+//! of Student's t-distribution with `n - p` degrees of freedom. For a single feature
+//! the quadratic form equals the textbook `1 / n + (x - mean)^2 / Sxx`.
+//!
+//! Weighted fits use `X^T * W * X` and the weighted `SSE`, the prediction interval is then
+//! the one of a new observation of weight 1.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::linalg::Matrix;
+use crate::model::dot;
+use crate::solver::weighted_system;
+
+/// Interval around the estimate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntervalKind {
+	/// Range of the mean response for the key
+	Confidence,
+	/// Range of a new observation for the key, always wider than the confidence interval
+	#[default]
+	Prediction,
+}
+
+/// Accepts `confidence` and `prediction`
+impl FromStr for IntervalKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"confidence" => Ok(IntervalKind::Confidence),
+			"prediction" => Ok(IntervalKind::Prediction),
+			_ => Err(format!("Unknown interval {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for IntervalKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			IntervalKind::Confidence => write!(f, "confidence"),
+			IntervalKind::Prediction => write!(f, "prediction"),
+		}
+	}
+}
+
+/// Statistics of the training set needed for the intervals
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inference {
+	/// Residual standard error `s`
+	pub residual_se: f64,
+	/// Number of training samples
+	pub n: usize,
+	/// `(X^T * W * X)^-1` of the raw training features with the intercept column
+	pub covariance: Matrix,
+}
+
+impl Inference {
+	/// Computes the statistics of theta fitted on the samples.
+	///
+	/// Returns `Option::None` if there are no more samples than coefficients
+	/// or the features are collinear.
+	pub fn fit(samples: &[Sample], theta: &[f64]) -> Option<Self> {
+		let n = samples.len();
+		if n <= theta.len() {
+			return Option::None;
+		}
+		let (x, _) = weighted_system(samples, theta.len() - 1);
+		let covariance = x.transpose().mul(&x).inverse()?;
+		let sse: f64 = samples
+			.iter()
+			.map(|s| s.weight * (s.target - dot(theta, &s.features)).powi(2))
+			.sum();
+		Some(Self {
+			residual_se: (sse / (n - theta.len()) as f64).sqrt(),
+			n,
+			covariance,
+		})
+	}
+
+	/// Degrees of freedom of the residuals, `n - p`
+	pub fn degrees_of_freedom(&self) -> usize {
+		self.n - self.covariance.rows()
+	}
+
+	/// Standard error of the estimate for given feature vector
+	pub fn standard_error(&self, features: &[f64], kind: IntervalKind) -> f64 {
+		let x0: Vec<f64> = std::iter::once(1.0)
+			.chain(features.iter().cloned())
+			.collect();
+		let leverage: f64 = self
+			.covariance
+			.mul_vec(&x0)
+			.iter()
+			.zip(x0.iter())
+			.map(|(a, b)| a * b)
+			.sum();
+		match kind {
+			IntervalKind::Confidence => self.residual_se * leverage.sqrt(),
+			IntervalKind::Prediction => self.residual_se * (1.0 + leverage).sqrt(),
+		}
+	}
+
+	/// Lower and upper bound of the two-sided interval at `level`, e.g. `0.95`
+	pub fn interval(
+		&self,
+		estimate: f64,
+		features: &[f64],
+		kind: IntervalKind,
+		level: f64,
+	) -> (f64, f64) {
+		let t = student_t_quantile(0.5 + level / 2.0, self.degrees_of_freedom() as f64);
+		let margin = t * self.standard_error(features, kind);
+		(estimate - margin, estimate + margin)
+	}
+}
+
+/// Quantile of Student's t-distribution with `dof` degrees of freedom
+///
+/// Found by bisection of the cumulative distribution function, `p` has to be in `(0, 1)`.
+pub fn student_t_quantile(p: f64, dof: f64) -> f64 {
+	if p < 0.5 {
+		return -student_t_quantile(1.0 - p, dof);
+	}
+	let mut upper = 1.0;
+	while student_t_cdf(upper, dof) < p && upper < 1e12 {
+		upper *= 2.0;
+	}
+	let mut lower = 0.0;
+	for _ in 0..200 {
+		let mid = (lower + upper) / 2.0;
+		if student_t_cdf(mid, dof) < p {
+			lower = mid;
+		} else {
+			upper = mid;
+		}
+	}
+	(lower + upper) / 2.0
+}
+
+/// Cumulative distribution function of Student's t-distribution
+pub fn student_t_cdf(t: f64, dof: f64) -> f64 {
+	let tail = 0.5 * incomplete_beta(dof / 2.0, 0.5, dof / (dof + t * t));
+	match t > 0.0 {
+		true => 1.0 - tail,
+		false => tail,
+	}
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+	if x <= 0.0 {
+		return 0.0;
+	}
+	if x >= 1.0 {
+		return 1.0;
+	}
+	let front =
+		(ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+	// The continued fraction converges fast only below the mean
+	match x < (a + 1.0) / (a + b + 2.0) {
+		true => front * beta_fraction(a, b, x) / a,
+		false => 1.0 - front * beta_fraction(b, a, 1.0 - x) / b,
+	}
+}
+
+/// Continued fraction of the incomplete beta function, modified Lentz's method
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+	const TINY: f64 = 1e-300;
+	let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+	let mut c = 1.0;
+	let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+	let mut h = d;
+	for m in 1..300 {
+		let m = m as f64;
+		let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+		d = 1.0 / clamp(1.0 + even * d);
+		c = clamp(1.0 + even / c);
+		h *= d * c;
+		let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+		d = 1.0 / clamp(1.0 + odd * d);
+		c = clamp(1.0 + odd / c);
+		let delta = d * c;
+		h *= delta;
+		if (delta - 1.0).abs() < 1e-15 {
+			break;
+		}
+	}
+	h
+}
+
+/// Natural logarithm of the gamma function, Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+	const COEFFICIENTS: [f64; 9] = [
+		0.999_999_999_999_809_9,
+		676.520_368_121_885_1,
+		-1_259.139_216_722_402_8,
+		771.323_428_777_653_1,
+		-176.615_029_162_140_6,
+		12.507_343_278_686_905,
+		-0.138_571_095_265_720_12,
+		9.984_369_578_019_572e-6,
+		1.505_632_735_149_311_6e-7,
+	];
+	if x < 0.5 {
+		// Reflection formula
+		let pi = std::f64::consts::PI;
+		return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+	}
+	let x = x - 1.0;
+	let t = x + 7.5;
+	let series = COEFFICIENTS[1..]
+		.iter()
+		.enumerate()
+		.fold(COEFFICIENTS[0], |acc, (i, c)| {
+			acc + c / (x + i as f64 + 1.0)
+		});
+	0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_quantiles() {
+		for (p, dof, expected) in [
+			(0.975, 1.0, 12.706204736174698),
+			(0.975, 10.0, 2.2281388519649385),
+			(0.95, 5.0, 2.015048373333024),
+			(0.995, 30.0, 2.749995653739),
+			(0.975, 1e6, 1.959966),
+		] {
+			let t = student_t_quantile(p, dof);
+			assert!((t - expected).abs() < 1e-6, "{} {} {}", p, dof, t);
+			assert!((student_t_quantile(1.0 - p, dof) + t).abs() < 1e-9);
+		}
+	}
+
+	#[test]
+	fn single_feature() {
+		// Residuals alternate +-1 around y = 1 + 2x
+		let samples: Vec<Sample> = (0..8)
+			.map(|i| {
+				let x = i as f64;
+				Sample::new(vec![x], 1.0 + 2.0 * x + if i % 2 == 0 { 1.0 } else { -1.0 })
+			})
+			.collect();
+		let theta = crate::solver::qr(&samples, 1).unwrap();
+		let inference = Inference::fit(&samples, &theta).unwrap();
+		assert_eq!((inference.n, inference.degrees_of_freedom()), (8, 6));
+		let (mean, sxx) = (3.5, 42.0);
+		let sse: f64 = samples
+			.iter()
+			.map(|s| (s.target - dot(&theta, &s.features)).powi(2))
+			.sum();
+		let s = (sse / 6.0).sqrt();
+		assert!((inference.residual_se - s).abs() < 1e-12);
+		for x in [-2.0, 3.5, 10.0] {
+			let h: f64 = 1.0 / 8.0 + (x - mean) * (x - mean) / sxx;
+			let confidence = inference.standard_error(&[x], IntervalKind::Confidence);
+			let prediction = inference.standard_error(&[x], IntervalKind::Prediction);
+			assert!((confidence - s * h.sqrt()).abs() < 1e-9);
+			assert!((prediction - s * (1.0 + h).sqrt()).abs() < 1e-9);
+		}
+		let (lower, upper) = inference.interval(8.0, &[3.5], IntervalKind::Prediction, 0.95);
+		assert!((8.0 - lower - (upper - 8.0)).abs() < 1e-9);
+		assert!(
+			((upper - lower) / 2.0 - 2.4469118511449692 * s * (1.0 + 1.0 / 8.0_f64).sqrt()).abs()
+				< 1e-6
+		);
+		assert_eq!(Inference::fit(&samples[..2], &theta), None);
+	}
+}
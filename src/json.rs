@@ -0,0 +1,335 @@
+//! Minimal JSON reader and writer used by the model files
+//!
+//! Objects keep the order of their keys and numbers keep their literal text,
+//! so `u64` and `f64` values survive the round trip exactly.
+use std::fmt::{self, Write};
+
+/// JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+	Null,
+	Bool(bool),
+	/// Literal text of the number
+	Number(String),
+	String(String),
+	Array(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+impl Value {
+	/// Number from float, non-finite values are stored as `null`
+	pub fn number(x: f64) -> Self {
+		match x.is_finite() {
+			// Debug formatting is the shortest text that parses back to the same float
+			true => Value::Number(format!("{:?}", x)),
+			false => Value::Null,
+		}
+	}
+
+	/// Object member by key
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+			_ => None,
+		}
+	}
+
+	/// Float value, `null` reads as `f64::NAN`
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			Value::Number(n) => n.parse().ok(),
+			Value::Null => Some(f64::NAN),
+			_ => None,
+		}
+	}
+
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			Value::Number(n) => n.parse().ok(),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(s) => Some(s),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[Value]> {
+		match self {
+			Value::Array(values) => Some(values),
+			_ => None,
+		}
+	}
+
+	pub fn as_object(&self) -> Option<&[(String, Value)]> {
+		match self {
+			Value::Object(members) => Some(members),
+			_ => None,
+		}
+	}
+
+	/// Parses the whole text as a single value
+	pub fn parse(text: &str) -> Result<Self, String> {
+		let mut parser = Parser {
+			chars: text.char_indices().peekable(),
+			text,
+		};
+		let value = parser.value()?;
+		parser.skip_whitespace();
+		match parser.chars.next() {
+			None => Ok(value),
+			Some((pos, _)) => Err(format!("Unexpected content at offset {}", pos)),
+		}
+	}
+
+	fn write(&self, out: &mut String, indent: usize) -> fmt::Result {
+		let pad = |depth: usize| "\t".repeat(depth);
+		match self {
+			Value::Null => out.push_str("null"),
+			Value::Bool(b) => write!(out, "{}", b)?,
+			Value::Number(n) => out.push_str(n),
+			Value::String(s) => write_string(out, s)?,
+			Value::Array(values) if values.is_empty() => out.push_str("[]"),
+			Value::Object(members) if members.is_empty() => out.push_str("{}"),
+			Value::Array(values) => {
+				out.push('[');
+				for (i, value) in values.iter().enumerate() {
+					out.push_str(if i == 0 { "\n" } else { ",\n" });
+					out.push_str(&pad(indent + 1));
+					value.write(out, indent + 1)?;
+				}
+				write!(out, "\n{}]", pad(indent))?;
+			}
+			Value::Object(members) => {
+				out.push('{');
+				for (i, (key, value)) in members.iter().enumerate() {
+					out.push_str(if i == 0 { "\n" } else { ",\n" });
+					out.push_str(&pad(indent + 1));
+					write_string(out, key)?;
+					out.push_str(": ");
+					value.write(out, indent + 1)?;
+				}
+				write!(out, "\n{}}}", pad(indent))?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Pretty printed with tab indentation
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut out = String::new();
+		self.write(&mut out, 0)?;
+		f.write_str(&out)
+	}
+}
+
+fn write_string(out: &mut String, s: &str) -> fmt::Result {
+	out.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	Ok(())
+}
+
+struct Parser<'a> {
+	chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+	text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+	fn skip_whitespace(&mut self) {
+		while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+	}
+
+	fn offset(&mut self) -> usize {
+		self.chars.peek().map_or(self.text.len(), |(pos, _)| *pos)
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), String> {
+		self.skip_whitespace();
+		match self.chars.next() {
+			Some((_, c)) if c == expected => Ok(()),
+			Some((pos, c)) => Err(format!(
+				"Expected {:?} at offset {}, found {:?}",
+				expected, pos, c
+			)),
+			None => Err(format!("Expected {:?}, found end of input", expected)),
+		}
+	}
+
+	fn value(&mut self) -> Result<Value, String> {
+		self.skip_whitespace();
+		let start = self.offset();
+		match self.chars.peek().map(|(_, c)| *c) {
+			None => Err(String::from("Unexpected end of input")),
+			Some('{') => self.object(),
+			Some('[') => self.array(),
+			Some('"') => Ok(Value::String(self.string()?)),
+			Some('-' | '0'..='9') => {
+				while self
+					.chars
+					.next_if(|(_, c)| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+					.is_some()
+				{}
+				let literal = &self.text[start..self.offset()];
+				match literal.parse::<f64>() {
+					Ok(_) => Ok(Value::Number(literal.to_string())),
+					Err(_) => Err(format!("Invalid number {:?} at offset {}", literal, start)),
+				}
+			}
+			Some(_) => {
+				while self
+					.chars
+					.next_if(|(_, c)| c.is_ascii_alphabetic())
+					.is_some()
+				{}
+				match &self.text[start..self.offset()] {
+					"null" => Ok(Value::Null),
+					"true" => Ok(Value::Bool(true)),
+					"false" => Ok(Value::Bool(false)),
+					_ => Err(format!("Unexpected content at offset {}", start)),
+				}
+			}
+		}
+	}
+
+	fn object(&mut self) -> Result<Value, String> {
+		self.expect('{')?;
+		let mut members = Vec::new();
+		self.skip_whitespace();
+		if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+			return Ok(Value::Object(members));
+		}
+		loop {
+			self.skip_whitespace();
+			let key = self.string()?;
+			self.expect(':')?;
+			members.push((key, self.value()?));
+			self.skip_whitespace();
+			match self.chars.next() {
+				Some((_, ',')) => continue,
+				Some((_, '}')) => return Ok(Value::Object(members)),
+				Some((pos, _)) => return Err(format!("Expected ',' or '}}' at offset {}", pos)),
+				None => return Err(String::from("Unterminated object")),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Value, String> {
+		self.expect('[')?;
+		let mut values = Vec::new();
+		self.skip_whitespace();
+		if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+			return Ok(Value::Array(values));
+		}
+		loop {
+			values.push(self.value()?);
+			self.skip_whitespace();
+			match self.chars.next() {
+				Some((_, ',')) => continue,
+				Some((_, ']')) => return Ok(Value::Array(values)),
+				Some((pos, _)) => return Err(format!("Expected ',' or ']' at offset {}", pos)),
+				None => return Err(String::from("Unterminated array")),
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		self.expect('"')?;
+		let mut s = String::new();
+		loop {
+			match self.chars.next() {
+				None => return Err(String::from("Unterminated string")),
+				Some((_, '"')) => return Ok(s),
+				Some((pos, '\\')) => match self.chars.next().map(|(_, c)| c) {
+					Some('"') => s.push('"'),
+					Some('\\') => s.push('\\'),
+					Some('/') => s.push('/'),
+					Some('b') => s.push('\u{8}'),
+					Some('f') => s.push('\u{c}'),
+					Some('n') => s.push('\n'),
+					Some('r') => s.push('\r'),
+					Some('t') => s.push('\t'),
+					Some('u') => {
+						let hex: String = (0..4)
+							.filter_map(|_| self.chars.next().map(|(_, c)| c))
+							.collect();
+						let c = u32::from_str_radix(&hex, 16)
+							.ok()
+							.and_then(char::from_u32)
+							.ok_or_else(|| format!("Invalid escape at offset {}", pos))?;
+						s.push(c);
+					}
+					_ => return Err(format!("Invalid escape at offset {}", pos)),
+				},
+				Some((_, c)) => s.push(c),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn roundtrip() {
+		let value = Value::Object(vec![
+			(
+				String::from("name"),
+				Value::String(String::from("a \"b\"\n\u{1}")),
+			),
+			(String::from("seed"), Value::Number(u64::MAX.to_string())),
+			(
+				String::from("theta"),
+				Value::Array(vec![Value::number(0.1), Value::number(-1e-300)]),
+			),
+			(String::from("nan"), Value::number(f64::NAN)),
+			(String::from("empty"), Value::Array(vec![])),
+			(String::from("flag"), Value::Bool(true)),
+		]);
+		let parsed = Value::parse(&value.to_string()).unwrap();
+		assert_eq!(parsed, value);
+		assert_eq!(parsed.get("seed").unwrap().as_u64(), Some(u64::MAX));
+		let theta = parsed.get("theta").unwrap().as_array().unwrap();
+		assert_eq!(theta[0].as_f64(), Some(0.1));
+		assert!(parsed.get("nan").unwrap().as_f64().unwrap().is_nan());
+	}
+
+	#[test]
+	fn malformed() {
+		for text in [
+			"",
+			"{",
+			"[1,]",
+			"{\"a\" 1}",
+			"\"abc",
+			"tru",
+			"1 2",
+			"{\"a\":-}",
+		] {
+			assert!(Value::parse(text).is_err(), "{:?}", text);
+		}
+		assert_eq!(
+			Value::parse(" [ 1 , {\"\\u0041\" : null} ] ").unwrap(),
+			Value::Array(vec![
+				Value::Number(String::from("1")),
+				Value::Object(vec![(String::from("A"), Value::Null)])
+			])
+		);
+	}
+}
@@ -0,0 +1,54 @@
+//! # ft_linear_regression
+//! Library behind the `train` and `predict` binaries.
+//!
+//! As per [subject](../../../resources/ft_linear_regression.en.pdf) the followig formulas are used:
+//! - price estimation `estimatePrice(mileage) = theta0 + (theta1 * mileage)`
+//! - training formulas
+//!     - `tmp_theta0 = learningRate * (1 / m) * Sum(i=0; m-1)(estimatePrice(mileage[i]) - price[i])`
+//!     - `tmp_theta1 = learningRate * (1 / m) * Sum(i=0; m-1)(estimatePrice(mileage[i]) - price[i]) * mileage[i]`
+//!     - where m is length of the dataset
+//!
+//! ## Modules
+//! - [`csv`] RFC 4180 reader used to load the input data
+//! - [`dataset`] loading and splitting of the input data
+//! - [`missing`] policies for missing and invalid values in the input data
+//! - [`model`] the linear model itself, with the [`domain`] it was trained on
+//! - [`optimizer`] gradient descent used by the trainer, with learning rate [`schedule`]s,
+//!   feature scaling by [`scaler`] and robust [`loss`] functions
+//! - [`solver`] closed-form least squares solvers, backed by [`linalg`], and [`robust`] fits
+//!   ignoring outliers
+//! - [`regularization`] ridge, lasso and elastic net penalties for the gradient descent and the solvers,
+//!   with lambda picked along the regularization [`path`]
+//! - [`metrics`] model quality measures, estimated by [`validation`] across folds
+//! - [`inference`] confidence and prediction intervals of the fit
+//! - [`model_io`] saving and loading of trained models, JSON or legacy text
+//! - [`trainer`] and [`predictor`] tie the above together
+pub mod csv;
+pub mod dataset;
+pub mod domain;
+pub mod inference;
+mod json;
+pub mod linalg;
+pub mod loss;
+pub mod metrics;
+pub mod missing;
+pub mod model;
+pub mod model_io;
+pub mod optimizer;
+mod params;
+pub mod path;
+pub mod predictor;
+pub mod regularization;
+pub mod result;
+pub mod robust;
+pub mod scaler;
+pub mod schedule;
+pub mod solver;
+pub mod trainer;
+pub mod validation;
+
+pub use dataset::Dataset;
+pub use model::LinearModel;
+pub use predictor::Predictor;
+pub use result::{Result, TrainError};
+pub use trainer::{Trainer, TrainerContext};
@@ -0,0 +1,277 @@
+//! Minimal dense linear algebra used by the closed-form solvers
+//!
+//! Matrices are small (features + 1 columns) so simple row-major storage
+//! and textbook algorithms are sufficient.
+use std::ops::{Index, IndexMut};
+
+/// Relative tolerance under which a pivot is considered zero
+const SINGULAR_EPS: f64 = 1e-12;
+
+/// Dense row-major matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+	rows: usize,
+	cols: usize,
+	data: Vec<f64>,
+}
+
+impl Matrix {
+	/// Matrix of zeros
+	pub fn zeros(rows: usize, cols: usize) -> Self {
+		Self {
+			rows,
+			cols,
+			data: vec![0.0; rows * cols],
+		}
+	}
+
+	/// Identity matrix
+	pub fn identity(n: usize) -> Self {
+		let mut m = Self::zeros(n, n);
+		for i in 0..n {
+			m[(i, i)] = 1.0;
+		}
+		m
+	}
+
+	/// Builds the matrix from rows, all rows have to be of the same length
+	pub fn from_rows(rows: &[Vec<f64>]) -> Self {
+		let cols = rows.first().map_or(0, |r| r.len());
+		assert!(rows.iter().all(|r| r.len() == cols), "Ragged rows");
+		Self {
+			rows: rows.len(),
+			cols,
+			data: rows.concat(),
+		}
+	}
+
+	/// Number of rows
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+
+	/// Number of columns
+	pub fn cols(&self) -> usize {
+		self.cols
+	}
+
+	/// Row slice
+	pub fn row(&self, i: usize) -> &[f64] {
+		&self.data[i * self.cols..(i + 1) * self.cols]
+	}
+
+	/// Transposed copy
+	pub fn transpose(&self) -> Self {
+		let mut t = Self::zeros(self.cols, self.rows);
+		for i in 0..self.rows {
+			for j in 0..self.cols {
+				t[(j, i)] = self[(i, j)];
+			}
+		}
+		t
+	}
+
+	/// Matrix product `self * other`
+	pub fn mul(&self, other: &Matrix) -> Self {
+		assert_eq!(self.cols, other.rows, "Dimension mismatch");
+		let mut m = Self::zeros(self.rows, other.cols);
+		for i in 0..self.rows {
+			for k in 0..self.cols {
+				let a = self[(i, k)];
+				for j in 0..other.cols {
+					m[(i, j)] += a * other[(k, j)];
+				}
+			}
+		}
+		m
+	}
+
+	/// Matrix vector product `self * v`
+	pub fn mul_vec(&self, v: &[f64]) -> Vec<f64> {
+		assert_eq!(self.cols, v.len(), "Dimension mismatch");
+		(0..self.rows)
+			.map(|i| self.row(i).iter().zip(v.iter()).map(|(a, b)| a * b).sum())
+			.collect()
+	}
+
+	/// Solves `self * x = b` by Gaussian elimination with partial pivoting.
+	///
+	/// Returns `Option::None` for singular matrix.
+	pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+		let x = self.solve_many(&Matrix::from_rows(
+			&b.iter().map(|v| vec![*v]).collect::<Vec<_>>(),
+		))?;
+		Some(x.data)
+	}
+
+	/// Inverse of the square matrix, `Option::None` if it is singular
+	pub fn inverse(&self) -> Option<Self> {
+		self.solve_many(&Self::identity(self.rows))
+	}
+
+	/// Solves `self * X = B` for all columns of `B` at once
+	fn solve_many(&self, b: &Matrix) -> Option<Matrix> {
+		assert_eq!(self.rows, self.cols, "Matrix is not square");
+		assert_eq!(self.rows, b.rows, "Dimension mismatch");
+		let n = self.rows;
+		let mut a = self.clone();
+		let mut x = b.clone();
+		let scale = a.data.iter().fold(0.0_f64, |acc, v| acc.max(v.abs()));
+		for col in 0..n {
+			// Partial pivoting
+			let pivot =
+				(col..n).max_by(|i, j| a[(*i, col)].abs().total_cmp(&a[(*j, col)].abs()))?;
+			if a[(pivot, col)].abs() <= SINGULAR_EPS * scale {
+				return None;
+			}
+			a.swap_rows(col, pivot);
+			x.swap_rows(col, pivot);
+			for row in 0..n {
+				if row == col {
+					continue;
+				}
+				let factor = a[(row, col)] / a[(col, col)];
+				if factor == 0.0 {
+					continue;
+				}
+				for k in col..n {
+					a[(row, k)] -= factor * a[(col, k)];
+				}
+				for k in 0..x.cols {
+					x[(row, k)] -= factor * x[(col, k)];
+				}
+			}
+		}
+		for row in 0..n {
+			for k in 0..x.cols {
+				x[(row, k)] /= a[(row, row)];
+			}
+		}
+		Some(x)
+	}
+
+	fn swap_rows(&mut self, i: usize, j: usize) {
+		if i != j {
+			for k in 0..self.cols {
+				self.data.swap(i * self.cols + k, j * self.cols + k);
+			}
+		}
+	}
+
+	/// Least squares solution of `self * x = b` using Householder QR decomposition.
+	///
+	/// Avoids forming `self^T * self`, so it is numerically stable for poorly scaled data.
+	/// Returns `Option::None` if the matrix doesn't have full column rank.
+	pub fn lstsq(&self, b: &[f64]) -> Option<Vec<f64>> {
+		assert_eq!(self.rows, b.len(), "Dimension mismatch");
+		let (m, n) = (self.rows, self.cols);
+		if m < n {
+			return None;
+		}
+		let mut r = self.clone();
+		let mut qtb = b.to_vec();
+		let scale = r.data.iter().fold(0.0_f64, |acc, v| acc.max(v.abs()));
+		for k in 0..n {
+			// Householder vector for column k below the diagonal
+			let norm = (k..m).map(|i| r[(i, k)].powi(2)).sum::<f64>().sqrt();
+			if norm <= SINGULAR_EPS * scale {
+				return None;
+			}
+			let alpha = if r[(k, k)] > 0.0 { -norm } else { norm };
+			let mut v: Vec<f64> = (k..m).map(|i| r[(i, k)]).collect();
+			v[0] -= alpha;
+			let v_norm2: f64 = v.iter().map(|x| x * x).sum();
+			if v_norm2 == 0.0 {
+				continue;
+			}
+			// Apply reflection to remaining columns and to b
+			for j in k..n {
+				let dot: f64 = (k..m).map(|i| v[i - k] * r[(i, j)]).sum();
+				let f = 2.0 * dot / v_norm2;
+				for i in k..m {
+					r[(i, j)] -= f * v[i - k];
+				}
+			}
+			let dot: f64 = (k..m).map(|i| v[i - k] * qtb[i]).sum();
+			let f = 2.0 * dot / v_norm2;
+			for i in k..m {
+				qtb[i] -= f * v[i - k];
+			}
+		}
+		// Back substitution of R * x = Q^T * b
+		let mut x = vec![0.0; n];
+		for i in (0..n).rev() {
+			let sum: f64 = ((i + 1)..n).map(|j| r[(i, j)] * x[j]).sum();
+			if r[(i, i)].abs() <= SINGULAR_EPS * scale {
+				return None;
+			}
+			x[i] = (qtb[i] - sum) / r[(i, i)];
+		}
+		Some(x)
+	}
+}
+
+impl Index<(usize, usize)> for Matrix {
+	type Output = f64;
+
+	fn index(&self, (i, j): (usize, usize)) -> &f64 {
+		&self.data[i * self.cols + j]
+	}
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+	fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+		&mut self.data[i * self.cols + j]
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn assert_close(a: &[f64], b: &[f64]) {
+		assert_eq!(a.len(), b.len());
+		for (x, y) in a.iter().zip(b.iter()) {
+			assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b);
+		}
+	}
+
+	#[test]
+	fn solve_and_inverse() {
+		let a = Matrix::from_rows(&[
+			vec![0.0, 2.0, 1.0],
+			vec![1.0, 1.0, 0.0],
+			vec![3.0, 0.0, 1.0],
+		]);
+		let x = a.solve(&[5.0, 3.0, 4.0]).unwrap();
+		assert_close(&x, &[1.0, 2.0, 1.0]);
+		let inv = a.inverse().unwrap();
+		assert_eq!(a.mul(&inv).rows(), 3);
+		for i in 0..3 {
+			let mut e = vec![0.0; 3];
+			e[i] = 1.0;
+			assert_close(a.mul(&inv).row(i), &e);
+		}
+		let singular = Matrix::from_rows(&[vec![1.0, 2.0], vec![2.0, 4.0]]);
+		assert!(singular.solve(&[1.0, 2.0]).is_none());
+	}
+
+	#[test]
+	fn least_squares() {
+		// Noisy points around a line
+		let a = Matrix::from_rows(&[
+			vec![1.0, 0.0],
+			vec![1.0, 1.0],
+			vec![1.0, 2.0],
+			vec![1.0, 3.0],
+		]);
+		let b = [1.5, 2.5, 5.5, 6.5];
+		let x = a.lstsq(&b).unwrap();
+		let at = a.transpose();
+		let normal = at.mul(&a).solve(&at.mul_vec(&b)).unwrap();
+		assert_close(&x, &normal);
+		assert_close(&x, &[1.3, 1.8]);
+		let rank_deficient = Matrix::from_rows(&[vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]]);
+		assert!(rank_deficient.lstsq(&[1.0, 2.0, 3.0]).is_none());
+	}
+}
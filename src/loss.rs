@@ -0,0 +1,296 @@
+//! Loss functions minimized by the gradient descent
+//!
+//! The cost is the weighted mean loss of the residuals `r = estimate(x[i]) - y[i]`:
+//! ```text
+//! cost = (1 / W) * Sum(i)w[i] * loss(r[i])
+//! gradient[j] = (1 / W) * Sum(i)w[i] * loss'(r[i]) * x[i][j]
+//! ```
+//! where `w[i]` is the weight of the sample and `W` the sum of the weights, `m` if unweighted.
+//! Squared loss is halved, so its gradient is the usual least squares one. The losses
+//! apply to the residuals of the scaled target, see `TrainerContext::target_scaler`.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::model::dot;
+use crate::params::Spec;
+
+/// Loss of a single residual
+pub trait Loss: fmt::Debug {
+	/// Loss of the residual `estimate - target`
+	fn value(&self, residual: f64) -> f64;
+	/// Derivative of the loss by the residual, a subgradient where it is not differentiable
+	fn derivative(&self, residual: f64) -> f64;
+}
+
+/// Least squares, `r^2 / 2`, fits the conditional mean
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Squared;
+
+impl Loss for Squared {
+	fn value(&self, residual: f64) -> f64 {
+		residual * residual / 2.0
+	}
+
+	fn derivative(&self, residual: f64) -> f64 {
+		residual
+	}
+}
+
+/// Least absolute deviations, `|r|`, fits the conditional median
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Absolute;
+
+impl Loss for Absolute {
+	fn value(&self, residual: f64) -> f64 {
+		residual.abs()
+	}
+
+	fn derivative(&self, residual: f64) -> f64 {
+		match residual {
+			r if r > 0.0 => 1.0,
+			r if r < 0.0 => -1.0,
+			_ => 0.0,
+		}
+	}
+}
+
+/// Squared for residuals within `delta`, absolute beyond it
+#[derive(Debug, Clone, Copy)]
+pub struct Huber {
+	/// Residual where the loss turns linear
+	pub delta: f64,
+}
+
+impl Loss for Huber {
+	fn value(&self, residual: f64) -> f64 {
+		match residual.abs() {
+			r if r <= self.delta => r * r / 2.0,
+			r => self.delta * (r - self.delta / 2.0),
+		}
+	}
+
+	fn derivative(&self, residual: f64) -> f64 {
+		residual.clamp(-self.delta, self.delta)
+	}
+}
+
+/// `ln(cosh(r))`, smooth loss close to squared for small and absolute for large residuals
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogCosh;
+
+impl Loss for LogCosh {
+	fn value(&self, residual: f64) -> f64 {
+		// cosh overflows for large residuals
+		let r = residual.abs();
+		r + (-2.0 * r).exp().ln_1p() - std::f64::consts::LN_2
+	}
+
+	fn derivative(&self, residual: f64) -> f64 {
+		residual.tanh()
+	}
+}
+
+/// Pinball loss, fits the `tau` quantile of the target
+///
+/// Underestimates cost `tau * |r|`, overestimates `(1 - tau) * |r|`.
+#[derive(Debug, Clone, Copy)]
+pub struct Quantile {
+	/// Quantile in `(0, 1)`
+	pub tau: f64,
+}
+
+impl Loss for Quantile {
+	fn value(&self, residual: f64) -> f64 {
+		match residual {
+			r if r > 0.0 => (1.0 - self.tau) * r,
+			r => -self.tau * r,
+		}
+	}
+
+	fn derivative(&self, residual: f64) -> f64 {
+		match residual {
+			r if r > 0.0 => 1.0 - self.tau,
+			r if r < 0.0 => -self.tau,
+			_ => 0.0,
+		}
+	}
+}
+
+/// Sum of the sample weights
+pub fn total_weight(samples: &[Sample]) -> f64 {
+	samples.iter().map(|s| s.weight).sum()
+}
+
+/// Weighted mean loss of theta on the samples
+pub fn cost(loss: &dyn Loss, theta: &[f64], samples: &[Sample]) -> f64 {
+	let sum: f64 = samples
+		.iter()
+		.map(|s| s.weight * loss.value(dot(theta, &s.features) - s.target))
+		.sum();
+	sum / total_weight(samples)
+}
+
+/// Gradient of the weighted mean loss with respect to theta
+///
+/// Zero for a batch without any weight.
+pub fn gradient(loss: &dyn Loss, theta: &[f64], samples: &[Sample]) -> Vec<f64> {
+	let m_ratio = match total_weight(samples) {
+		w if w > 0.0 => 1.0 / w,
+		_ => 0.0,
+	};
+	let mut sum = vec![0.0; theta.len()];
+	for sample in samples.iter() {
+		let derivative =
+			sample.weight * loss.derivative(dot(theta, &sample.features) - sample.target);
+		sum[0] += derivative;
+		for (s, x) in sum[1..].iter_mut().zip(sample.features.iter()) {
+			*s += derivative * x;
+		}
+	}
+	sum.iter().map(|s| s * m_ratio).collect()
+}
+
+/// Loss selection with its parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LossKind {
+	#[default]
+	Squared,
+	Absolute,
+	Huber {
+		delta: f64,
+	},
+	LogCosh,
+	Quantile {
+		tau: f64,
+	},
+}
+
+impl LossKind {
+	/// Creates the loss
+	pub fn build(&self) -> Box<dyn Loss> {
+		match *self {
+			LossKind::Squared => Box::new(Squared),
+			LossKind::Absolute => Box::new(Absolute),
+			LossKind::Huber { delta } => Box::new(Huber { delta }),
+			LossKind::LogCosh => Box::new(LogCosh),
+			LossKind::Quantile { tau } => Box::new(Quantile { tau }),
+		}
+	}
+
+	/// Whether the loss has a continuous gradient
+	///
+	/// The cost of the others doesn't decrease monotonically near the minimum
+	/// with a constant learning rate.
+	pub fn is_smooth(&self) -> bool {
+		!matches!(self, LossKind::Absolute | LossKind::Quantile { .. })
+	}
+}
+
+/// Accepts `name[:param=value,...]`, e.g. `huber:delta=2` or `quantile:tau=0.9`
+///
+/// Names are `squared`, `absolute`, `huber`, `log-cosh` and `quantile`.
+/// Omitted parameters default to `delta = 1` and `tau = 0.5`.
+impl FromStr for LossKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let spec = Spec::parse(s)?;
+		let kind = match spec.name {
+			"squared" => LossKind::Squared,
+			"absolute" => LossKind::Absolute,
+			"huber" => match spec.get("delta", 1.0) {
+				delta if delta > 0.0 && delta.is_finite() => LossKind::Huber { delta },
+				delta => return Err(format!("Invalid huber delta {}, expected above 0", delta)),
+			},
+			"log-cosh" => LossKind::LogCosh,
+			"quantile" => match spec.get("tau", 0.5) {
+				tau if tau > 0.0 && tau < 1.0 => LossKind::Quantile { tau },
+				tau => return Err(format!("Invalid quantile tau {}, expected 0 to 1", tau)),
+			},
+			_ => return Err(format!("Unknown loss {:?}", spec.name)),
+		};
+		spec.finish(kind)
+	}
+}
+
+impl fmt::Display for LossKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LossKind::Squared => write!(f, "squared"),
+			LossKind::Absolute => write!(f, "absolute"),
+			LossKind::Huber { delta } => write!(f, "huber:delta={}", delta),
+			LossKind::LogCosh => write!(f, "log-cosh"),
+			LossKind::Quantile { tau } => write!(f, "quantile:tau={}", tau),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn derivatives() {
+		let kinds = [
+			"squared",
+			"absolute",
+			"huber:delta=1.5",
+			"log-cosh",
+			"quantile:tau=0.9",
+		];
+		for name in kinds.iter() {
+			let loss = name.parse::<LossKind>().unwrap().build();
+			assert_eq!(loss.value(0.0), 0.0, "{}", name);
+			for r in [-40.0, -2.0, -0.3, 0.7, 1.2, 3.0, 800.0] {
+				let h = 1e-6;
+				let numeric = (loss.value(r + h) - loss.value(r - h)) / (2.0 * h);
+				assert!(
+					(numeric - loss.derivative(r)).abs() < 1e-6 * r.abs().max(1.0),
+					"{} at {}: {} vs {}",
+					name,
+					r,
+					numeric,
+					loss.derivative(r)
+				);
+				assert!(loss.value(r) > 0.0, "{} at {}", name, r);
+			}
+		}
+		assert_eq!(Huber { delta: 1.0 }.value(3.0), 2.5);
+		assert_eq!(Quantile { tau: 0.9 }.value(-2.0), 1.8);
+		assert!((Quantile { tau: 0.9 }.value(2.0) - 0.2).abs() < 1e-12);
+		assert!((LogCosh.value(800.0) - (800.0 - std::f64::consts::LN_2)).abs() < 1e-9);
+	}
+
+	#[test]
+	fn median_and_quantile() {
+		// Minimizing the cost of an intercept only model over a grid
+		let samples: Vec<Sample> = [1.0, 2.0, 3.0, 4.0, 100.0]
+			.iter()
+			.map(|y| Sample::new(Vec::new(), *y))
+			.collect();
+		let argmin = |loss: &dyn Loss| {
+			(0..=1000)
+				.map(|i| i as f64 / 10.0)
+				.min_by(|a, b| cost(loss, &[*a], &samples).total_cmp(&cost(loss, &[*b], &samples)))
+				.unwrap()
+		};
+		assert_eq!(argmin(&Squared), 22.0);
+		assert_eq!(argmin(&Absolute), 3.0);
+		assert_eq!(argmin(&Quantile { tau: 0.7 }), 4.0);
+		assert!(argmin(&Huber { delta: 1.0 }) < 4.0);
+		assert_eq!(gradient(&Absolute, &[3.5], &samples), vec![0.2]);
+	}
+
+	#[test]
+	fn parse_kind() {
+		assert_eq!("huber".parse(), Ok(LossKind::Huber { delta: 1.0 }));
+		let kind = "quantile:tau=0.1".parse::<LossKind>().unwrap();
+		assert_eq!(kind.to_string().parse::<LossKind>(), Ok(kind));
+		assert!("quantile:tau=1".parse::<LossKind>().is_err());
+		assert!("huber:delta=0".parse::<LossKind>().is_err());
+		assert!("huber:tau=0.5".parse::<LossKind>().is_err());
+		assert!("hinge".parse::<LossKind>().is_err());
+		assert!(!kind.is_smooth() && LossKind::LogCosh.is_smooth());
+	}
+}
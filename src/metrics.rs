@@ -0,0 +1,296 @@
+//! Model quality metrics
+//!
+//! [`Metrics`] is the full suite of regression measures, evaluated on the train
+//! and test sets by the trainer and stored with the model.
+use std::fmt::Write;
+
+use crate::dataset::Dataset;
+use crate::model::LinearModel;
+
+/// Regression quality measures of a model over a dataset
+///
+/// Percentage errors are fractions, `0.1` is 10 %. Means, the median and the variances
+/// are weighted by the sample weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+	/// Number of samples
+	pub n: usize,
+	/// Mean absolute error
+	pub mae: f64,
+	/// Mean squared error
+	pub mse: f64,
+	/// Root mean squared error
+	pub rmse: f64,
+	/// Mean absolute percentage error, samples with zero target are left out
+	pub mape: f64,
+	/// Symmetric mean absolute percentage error, `2|y - y'| / (|y| + |y'|)`
+	pub smape: f64,
+	/// Median absolute error
+	pub median_ae: f64,
+	/// Largest absolute error
+	pub max_error: f64,
+	/// Coefficient of determination
+	pub r2: f64,
+	/// R² penalized for the number of features
+	pub adjusted_r2: f64,
+	/// `1 - Var(y - y') / Var(y)`, equals R² for unbiased residuals
+	pub explained_variance: f64,
+}
+
+impl Metrics {
+	/// Names of the measures, in the order of [`Metrics::values`]
+	pub const NAMES: [&'static str; 10] = [
+		"mae",
+		"mse",
+		"rmse",
+		"mape",
+		"smape",
+		"median_ae",
+		"max_error",
+		"r2",
+		"adjusted_r2",
+		"explained_variance",
+	];
+
+	/// Evaluates the model over given dataset.
+	///
+	/// Returns `Option::None` for empty set. Measures that aren't defined for the set,
+	/// e.g. R² of a constant target, are `f64::NAN`.
+	pub fn evaluate(model: &LinearModel, set: &Dataset) -> Option<Self> {
+		if set.is_empty() {
+			return Option::None;
+		}
+		let n = set.len() as f64;
+		let targets: Vec<f64> = set.samples.iter().map(|s| s.target).collect();
+		let weights: Vec<f64> = set.samples.iter().map(|s| s.weight).collect();
+		let estimates: Vec<f64> = set
+			.samples
+			.iter()
+			.map(|s| model.estimate(&s.features))
+			.collect();
+		let residuals: Vec<f64> = targets
+			.iter()
+			.zip(estimates.iter())
+			.map(|(y, e)| y - e)
+			.collect();
+		let errors: Vec<f64> = residuals.iter().map(|r| r.abs()).collect();
+
+		let mean = |values: &[f64], weights: &[f64]| {
+			values
+				.iter()
+				.zip(weights.iter())
+				.map(|(v, w)| v * w)
+				.sum::<f64>()
+				/ weights.iter().sum::<f64>()
+		};
+		let variance = |values: &[f64]| {
+			let m = mean(values, &weights);
+			let deviations: Vec<f64> = values.iter().map(|v| (v - m).powi(2)).collect();
+			mean(&deviations, &weights)
+		};
+		let squared: Vec<f64> = residuals.iter().map(|r| r * r).collect();
+		let mse = mean(&squared, &weights);
+		let (percentage, percentage_weights): (Vec<f64>, Vec<f64>) = residuals
+			.iter()
+			.zip(targets.iter())
+			.zip(weights.iter())
+			.filter(|((_, y), _)| **y != 0.0)
+			.map(|((r, y), w)| ((r / y).abs(), *w))
+			.unzip();
+		let symmetric: Vec<f64> = targets
+			.iter()
+			.zip(estimates.iter())
+			.map(|(y, e)| match y.abs() + e.abs() {
+				0.0 => 0.0,
+				d => 2.0 * (y - e).abs() / d,
+			})
+			.collect();
+		let r2 = 1.0 - mse / variance(&targets);
+		let dof = n - model.feature_count() as f64 - 1.0;
+		Some(Self {
+			n: set.len(),
+			mae: mean(&errors, &weights),
+			mse,
+			rmse: mse.sqrt(),
+			mape: mean(&percentage, &percentage_weights),
+			smape: mean(&symmetric, &weights),
+			median_ae: weighted_median(
+				&mut errors
+					.iter()
+					.cloned()
+					.zip(weights.iter().cloned())
+					.collect::<Vec<_>>(),
+			),
+			max_error: errors.iter().cloned().fold(0.0, f64::max),
+			r2,
+			adjusted_r2: match dof > 0.0 {
+				true => 1.0 - (1.0 - r2) * (n - 1.0) / dof,
+				false => f64::NAN,
+			},
+			explained_variance: 1.0 - variance(&residuals) / variance(&targets),
+		})
+	}
+
+	/// Values of the measures, in the order of [`Metrics::NAMES`]
+	pub fn values(&self) -> [f64; 10] {
+		[
+			self.mae,
+			self.mse,
+			self.rmse,
+			self.mape,
+			self.smape,
+			self.median_ae,
+			self.max_error,
+			self.r2,
+			self.adjusted_r2,
+			self.explained_variance,
+		]
+	}
+
+	/// Name and value pairs, names prefixed with `prefix`, e.g. `test_`
+	pub fn named(&self, prefix: &str) -> Vec<(String, f64)> {
+		Self::NAMES
+			.iter()
+			.zip(self.values())
+			.map(|(name, v)| (format!("{}{}", prefix, name), v))
+			.collect()
+	}
+}
+
+/// Table of the measures with a column for each set, missing sets are shown as `-`
+pub fn table(columns: &[(&str, Option<&Metrics>)]) -> String {
+	let mut out = format!("{:<20}", "metric");
+	for (title, _) in columns {
+		write!(out, "{:>16}", title).unwrap();
+	}
+	let rows = std::iter::once((
+		"n",
+		columns.iter().map(|(_, m)| m.map(|m| m.n as f64)).collect(),
+	))
+	.chain(Metrics::NAMES.iter().enumerate().map(|(i, name)| {
+		(
+			*name,
+			columns
+				.iter()
+				.map(|(_, m)| m.map(|m| m.values()[i]))
+				.collect::<Vec<_>>(),
+		)
+	}));
+	for (name, values) in rows {
+		write!(out, "\n{:<20}", name).unwrap();
+		for value in values {
+			match value {
+				Some(v) if name == "n" => write!(out, "{:>16}", v),
+				Some(v) => write!(out, "{:>16.4}", v),
+				None => write!(out, "{:>16}", "-"),
+			}
+			.unwrap();
+		}
+	}
+	out
+}
+
+/// Quantile `q` of sorted values, linearly interpolated between the closest ranks.
+///
+/// Returns `f64::NAN` for empty values.
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+	if sorted.is_empty() {
+		return f64::NAN;
+	}
+	let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+	let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+	sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Weighted median of `(value, weight)` pairs, `f64::NAN` without any weight
+///
+/// Smallest value with more than half of the weight at or below it. A value splitting
+/// the weight exactly in half is averaged with the next one, as the usual median of
+/// an even number of values is. Integer weights act as repetitions of the value.
+pub fn weighted_median(pairs: &mut [(f64, f64)]) -> f64 {
+	pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+	let half = pairs.iter().map(|(_, w)| w).sum::<f64>() / 2.0;
+	let mut weighted = pairs.iter().filter(|(_, w)| *w > 0.0);
+	let mut cumulative = 0.0;
+	while let Some((value, weight)) = weighted.next() {
+		cumulative += weight;
+		if cumulative > half {
+			return *value;
+		}
+		if cumulative == half {
+			return weighted
+				.next()
+				.map_or(*value, |(next, _)| (value + next) / 2.0);
+		}
+	}
+	f64::NAN
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::dataset::Sample;
+
+	#[test]
+	fn suite() {
+		let model = LinearModel::new(vec![String::from("x")], String::from("y"), vec![0.0, 1.0]);
+		// Estimates are x, errors 1, -1, 0, 2
+		let samples = [(1.0, 2.0), (2.0, 1.0), (3.0, 3.0), (4.0, 6.0)]
+			.iter()
+			.map(|(x, y)| Sample::new(vec![*x], *y))
+			.collect();
+		let set = Dataset::new(vec![String::from("x")], String::from("y"), samples);
+		let m = Metrics::evaluate(&model, &set).unwrap();
+		assert_eq!(m.n, 4);
+		assert_eq!(m.mae, 1.0);
+		assert_eq!(m.mse, 1.5);
+		assert_eq!(m.rmse, 1.5_f64.sqrt());
+		assert!((m.mape - (0.5 + 1.0 + 0.0 + 1.0 / 3.0) / 4.0).abs() < 1e-12);
+		assert!((m.smape - (2.0 / 3.0 + 2.0 / 3.0 + 0.0 + 0.4) / 4.0).abs() < 1e-12);
+		assert_eq!(m.median_ae, 1.0);
+		assert_eq!(m.max_error, 2.0);
+		// Var(y) = 3.5, residual mean 0.5 and variance 1.25
+		assert!((m.r2 - 4.0 / 7.0).abs() < 1e-12);
+		assert!((m.adjusted_r2 - 5.0 / 14.0).abs() < 1e-12);
+		assert!((m.explained_variance - (1.0 - 1.25 / 3.5)).abs() < 1e-12);
+		assert_eq!(m.named("test_")[7], (String::from("test_r2"), m.r2));
+
+		let table = table(&[("train", Some(&m)), ("test", None)]);
+		assert_eq!(table.lines().count(), 12);
+		assert!(table.contains("\nrmse                          1.2247               -"));
+		assert_eq!(Metrics::evaluate(&model, &set.empty_like()), None);
+	}
+
+	#[test]
+	fn weighted() {
+		let model = LinearModel::new(vec![String::from("x")], String::from("y"), vec![0.0, 1.0]);
+		// Errors 1, -1, 0, 2 with the last sample counting twice
+		let samples = [
+			(1.0, 2.0, 1.0),
+			(2.0, 1.0, 1.0),
+			(3.0, 3.0, 1.0),
+			(4.0, 6.0, 2.0),
+		]
+		.iter()
+		.map(|(x, y, w)| Sample::weighted(vec![*x], *y, *w))
+		.collect();
+		let set = Dataset::new(vec![String::from("x")], String::from("y"), samples);
+		let m = Metrics::evaluate(&model, &set).unwrap();
+		assert_eq!(m.n, 4);
+		assert_eq!(m.mae, 6.0 / 5.0);
+		assert_eq!(m.mse, 10.0 / 5.0);
+		assert_eq!(m.median_ae, 1.0);
+		assert_eq!(m.max_error, 2.0);
+		// Weighted mean of y is 18 / 5, Var(y) = 4.24
+		assert!((m.r2 - (1.0 - 2.0 / 4.24)).abs() < 1e-12);
+
+		let mut pairs = vec![(3.0, 1.0), (1.0, 1.0), (2.0, 1.0), (4.0, 1.0)];
+		assert_eq!(weighted_median(&mut pairs), 2.5);
+		assert_eq!(
+			weighted_median(&mut [(1.0, 1.0), (2.0, 3.0), (9.0, 0.0)]),
+			2.0
+		);
+		assert_eq!(weighted_median(&mut [(1.0, 3.0), (5.0, 1.0)]), 1.0);
+		assert!(weighted_median(&mut []).is_nan());
+	}
+}
@@ -0,0 +1,242 @@
+//! Handling of missing and invalid values during data load
+//!
+//! Every selected cell is classified as either a finite number or one of [`Invalid`] reasons.
+//! [`MissingPolicy`] then decides whether loading fails, the row is skipped or the cell is imputed.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::csv::Field;
+use crate::result::{Result, TrainError};
+
+/// Cell values treated as missing, compared case insensitively
+const MISSING_MARKERS: [&str; 6] = ["", "na", "n/a", "nan", "null", "none"];
+
+/// Reason why a cell is not usable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invalid {
+	/// Empty cell or a marker like `NA`
+	Missing,
+	/// Cell that is not a number
+	NonNumeric,
+	/// Infinite number
+	NonFinite,
+}
+
+impl Invalid {
+	/// Classifies the cell, returns its value if it is a finite number
+	pub fn classify(value: &str) -> std::result::Result<f64, Invalid> {
+		let value = value.trim();
+		if MISSING_MARKERS
+			.iter()
+			.any(|m| m.eq_ignore_ascii_case(value))
+		{
+			return Err(Invalid::Missing);
+		}
+		match value.parse::<f64>() {
+			Ok(v) if v.is_finite() => Ok(v),
+			Ok(_) => Err(Invalid::NonFinite),
+			Err(_) => Err(Invalid::NonNumeric),
+		}
+	}
+}
+
+impl fmt::Display for Invalid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Invalid::Missing => write!(f, "missing"),
+			Invalid::NonNumeric => write!(f, "non-numeric"),
+			Invalid::NonFinite => write!(f, "non-finite"),
+		}
+	}
+}
+
+/// Value used in place of invalid cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Imputation {
+	/// Mean of the valid values in the column
+	Mean,
+	/// Median of the valid values in the column
+	Median,
+	/// Given constant
+	Constant(f64),
+}
+
+/// Accepts `mean`, `median` or a number for constant
+impl FromStr for Imputation {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"mean" => Ok(Imputation::Mean),
+			"median" => Ok(Imputation::Median),
+			_ => match Invalid::classify(s) {
+				Ok(v) => Ok(Imputation::Constant(v)),
+				Err(_) => Err(format!("Unknown imputation {:?}", s)),
+			},
+		}
+	}
+}
+
+/// What to do with rows holding invalid cells
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MissingPolicy {
+	/// Fail with [`TrainError::InvalidValue`]
+	#[default]
+	Fail,
+	/// Skip the whole row
+	Skip,
+	/// Replace invalid feature cells. Rows with invalid target are skipped,
+	/// the target is never made up.
+	Impute(Imputation),
+}
+
+/// Accepts `fail`, `skip` and `impute`, which imputes the mean
+impl FromStr for MissingPolicy {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"fail" => Ok(MissingPolicy::Fail),
+			"skip" => Ok(MissingPolicy::Skip),
+			"impute" => Ok(MissingPolicy::Impute(Imputation::Mean)),
+			_ => Err(format!("Unknown missing value policy {:?}", s)),
+		}
+	}
+}
+
+/// Counts of invalid cells or rows per reason
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InvalidCounts {
+	pub missing: usize,
+	pub non_numeric: usize,
+	pub non_finite: usize,
+}
+
+impl InvalidCounts {
+	/// Increments counter of given reason
+	pub fn add(&mut self, reason: Invalid) {
+		match reason {
+			Invalid::Missing => self.missing += 1,
+			Invalid::NonNumeric => self.non_numeric += 1,
+			Invalid::NonFinite => self.non_finite += 1,
+		}
+	}
+
+	/// Sum of all reasons
+	pub fn total(&self) -> usize {
+		self.missing + self.non_numeric + self.non_finite
+	}
+}
+
+impl fmt::Display for InvalidCounts {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} missing, {} non-numeric, {} non-finite",
+			self.missing, self.non_numeric, self.non_finite
+		)
+	}
+}
+
+/// Summary of the data load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadReport {
+	/// Number of data records in the file
+	pub rows: usize,
+	/// Dropped rows, by reason of their first invalid cell
+	pub dropped: InvalidCounts,
+	/// Imputed cells
+	pub imputed: InvalidCounts,
+	/// Left out rows of zero weight
+	pub zero_weight: usize,
+}
+
+impl fmt::Display for LoadReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Loaded {} of {} rows\n\tdropped {} rows ({})\n\timputed {} cells ({})",
+			self.rows - self.dropped.total() - self.zero_weight,
+			self.rows,
+			self.dropped.total(),
+			self.dropped,
+			self.imputed.total(),
+			self.imputed
+		)?;
+		if self.zero_weight > 0 {
+			write!(f, "\n\tignored {} rows of zero weight", self.zero_weight)?;
+		}
+		Ok(())
+	}
+}
+
+/// Parses selected cell, keeping the field position for error reporting
+pub fn parse_field(field: &Field) -> std::result::Result<f64, (Invalid, &Field)> {
+	Invalid::classify(&field.value).map_err(|reason| (reason, field))
+}
+
+/// Converts invalid cell to an error for [`MissingPolicy::Fail`]
+pub fn to_error((reason, field): (Invalid, &Field)) -> TrainError {
+	TrainError::InvalidValue {
+		line: field.line,
+		column: field.column,
+		value: field.value.clone(),
+		reason,
+	}
+}
+
+/// Resolves imputation to the value for a column of valid values
+pub fn impute_value(imputation: Imputation, valid: &mut [f64]) -> Result<f64> {
+	match imputation {
+		Imputation::Constant(v) => Ok(v),
+		_ if valid.is_empty() => Err(TrainError::Custom(String::from(
+			"Can't impute column without any valid value",
+		))),
+		Imputation::Mean => Ok(valid.iter().sum::<f64>() / valid.len() as f64),
+		Imputation::Median => {
+			valid.sort_by(|a, b| a.partial_cmp(b).unwrap());
+			let mid = valid.len() / 2;
+			match valid.len() % 2 {
+				0 => Ok((valid[mid - 1] + valid[mid]) / 2.0),
+				_ => Ok(valid[mid]),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn classify() {
+		assert_eq!(Invalid::classify(" 1.5 "), Ok(1.5));
+		assert_eq!(Invalid::classify(""), Err(Invalid::Missing));
+		assert_eq!(Invalid::classify("NA"), Err(Invalid::Missing));
+		assert_eq!(Invalid::classify("NaN"), Err(Invalid::Missing));
+		assert_eq!(Invalid::classify("inf"), Err(Invalid::NonFinite));
+		assert_eq!(Invalid::classify("-Infinity"), Err(Invalid::NonFinite));
+		assert_eq!(Invalid::classify("abc"), Err(Invalid::NonNumeric));
+	}
+
+	#[test]
+	fn imputation() {
+		assert_eq!(
+			impute_value(Imputation::Mean, &mut [1.0, 2.0, 6.0]).unwrap(),
+			3.0
+		);
+		assert_eq!(
+			impute_value(Imputation::Median, &mut [6.0, 1.0, 2.0]).unwrap(),
+			2.0
+		);
+		assert_eq!(
+			impute_value(Imputation::Median, &mut [4.0, 1.0, 2.0, 6.0]).unwrap(),
+			3.0
+		);
+		assert_eq!(
+			impute_value(Imputation::Constant(7.0), &mut []).unwrap(),
+			7.0
+		);
+		assert!(impute_value(Imputation::Mean, &mut []).is_err());
+	}
+}
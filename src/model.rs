@@ -0,0 +1,53 @@
+//! Linear regression model
+//!
+//! Holds trained parameters and estimates values using formula:
+//! ```text
+//! estimate = theta[0] + (theta[1] * x[0]) + ... + (theta[n] * x[n - 1])
+//! ```
+
+/// Trained linear regression model
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearModel {
+	/// Feature labels
+	pub feature_labels: Vec<String>,
+	/// Target label
+	pub target_label: String,
+	/// Theta for the linear regression equations, `theta[0]` is the intercept
+	pub theta: Vec<f64>,
+}
+
+impl LinearModel {
+	/// Default constructor
+	pub fn new(feature_labels: Vec<String>, target_label: String, theta: Vec<f64>) -> Self {
+		Self {
+			feature_labels,
+			target_label,
+			theta,
+		}
+	}
+
+	/// Number of features the model expects
+	pub fn feature_count(&self) -> usize {
+		self.feature_labels.len()
+	}
+
+	/// Estimates value for given feature vector
+	///
+	/// Panics if the length of `features` doesn't match the model.
+	pub fn estimate(&self, features: &[f64]) -> f64 {
+		assert_eq!(
+			features.len() + 1,
+			self.theta.len(),
+			"Feature vector doesn't match the model"
+		);
+		dot(&self.theta, features)
+	}
+}
+
+/// Linear combination `theta[0] + Sum(j=1; n)(theta[j] * x[j - 1])`
+pub fn dot(theta: &[f64], features: &[f64]) -> f64 {
+	theta[1..]
+		.iter()
+		.zip(features.iter())
+		.fold(theta[0], |acc, (t, x)| acc + t * x)
+}
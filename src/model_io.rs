@@ -0,0 +1,927 @@
+//! Model input and output
+//!
+//! Models are stored in one of the [`Format`]s, the format is detected on load.
+//!
+//! The JSON format is versioned and self-describing:
+//! ```text
+//! {
+//!     "format_version": 1,
+//!     "model_type": "linear_regression",
+//!     "features": ["km"],
+//!     "target": "price",
+//!     "coefficients": [8499.6, -0.0214],
+//!     "scaler": {"features": [{"method": "minmax", "offset": 22899.0, "scale": 217101.0}], "target": ...},
+//!     "inference": {"residual_se": 668.0, "n": 19, "covariance": [[0.37, -2.1e-6], [-2.1e-6, 1.4e-11]]},
+//!     "domain": [{"min": 22899.0, "max": 240000.0, "quantiles": {"0.01": 25633.3, ...}}],
+//!     "regularization_path": {"rule": "1se", "lambda_min": 0.02, "lambda_1se": 0.4,
+//!         "lambdas": [1300.5, ...], "cv_mse": [...], "cv_se": [...], "coefficients": [[6331.8, 0.0], ...]},
+//!     "hyperparameters": {"solver": "gd", ...},
+//!     "seed": 42,
+//!     "dataset_hash": "8f1c0a6e2b3d4f50",
+//!     "metrics": {"train_mae": 512.3, ..., "test_r2": 0.71, ...},
+//!     "timestamp": "2022-03-01T12:00:00Z"
+//! }
+//! ```
+//! where `coefficients[0]` is the intercept. Coefficients always apply to raw values,
+//! the scaler is recorded only to document the training. Inference holds what the
+//! [`inference`](crate::inference) intervals need and is `null` if they can't be computed.
+//! The regularization [`path`](crate::path) is `null` unless lambda was picked along it.
+//!
+//! The binary format is compact, for devices. All numbers are little-endian:
+//! ```text
+//! magic "FTLR" | version: u16 | feature count: u32
+//! labels (features, then target): length: u32 | UTF-8 bytes
+//! coefficients: f64 * (feature count + 1)
+//! CRC-32 of all preceding bytes: u32
+//! ```
+//! Metadata is not stored. Files that fail the checks are rejected with a [`BinaryError`].
+//!
+//! The legacy format is a plain text file:
+//! ```text
+//! x_label_0 ... x_label_n y_label
+//! theta_0 theta_1 ... theta_n+1
+//! ```
+//! where labels are string and theta is [`f64`](https://doc.rust-lang.org/std/primitive.f64.html) compatible.
+//! Single feature model therefore keeps the original two column layout.
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::domain::{Domain, FeatureRange};
+use crate::inference::Inference;
+use crate::json::Value;
+use crate::linalg::Matrix;
+use crate::model::LinearModel;
+use crate::path::{LambdaRule, RegularizationPath};
+use crate::scaler::{SampleScaling, Scaling};
+
+/// Version written to the `format_version` field of JSON models
+pub const FORMAT_VERSION: u64 = 1;
+
+/// Value of the `model_type` field of JSON models
+pub const MODEL_TYPE: &str = "linear_regression";
+
+/// Model file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+	/// Versioned JSON with metadata
+	#[default]
+	Json,
+	/// Labels and theta on two whitespace separated lines, labels can't contain spaces
+	Legacy,
+	/// Little-endian binary with checksum, without metadata
+	Binary,
+}
+
+/// Accepts `json`, `legacy` and `binary`
+impl FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"json" => Ok(Format::Json),
+			"legacy" => Ok(Format::Legacy),
+			"binary" => Ok(Format::Binary),
+			_ => Err(format!("Unknown model format {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Format {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Format::Json => write!(f, "json"),
+			Format::Legacy => write!(f, "legacy"),
+			Format::Binary => write!(f, "binary"),
+		}
+	}
+}
+
+/// Information about the training stored along the model
+///
+/// Legacy files don't store any, so loading them gives the default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+	/// Scaling used by the gradient descent
+	pub scaling: Option<SampleScaling>,
+	/// Statistics for the confidence and prediction intervals
+	pub inference: Option<Inference>,
+	/// Training range of every feature
+	pub domain: Option<Domain>,
+	/// Regularization path lambda was picked from
+	pub path: Option<RegularizationPath>,
+	/// Training settings as name and value
+	pub hyperparameters: Vec<(String, String)>,
+	/// Seed of the train and test split
+	pub seed: Option<u64>,
+	/// [`Dataset::fingerprint`](crate::dataset::Dataset::fingerprint) of the loaded data
+	pub dataset_hash: Option<u64>,
+	/// Quality measures as name and value
+	pub metrics: Vec<(String, f64)>,
+	/// Time of the training, RFC 3339 in UTC
+	pub timestamp: Option<String>,
+}
+
+/// Model with its metadata, as stored in a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedModel {
+	pub model: LinearModel,
+	pub metadata: Metadata,
+}
+
+/// Saves labels and theta values into a file in the legacy format.
+///
+/// Labels that are empty or contain whitespace can't be read back and are
+/// rejected with [`ErrorKind::InvalidInput`].
+pub fn save(model: &LinearModel, path: &str) -> Result<()> {
+	let labels: Vec<&str> = model
+		.feature_labels
+		.iter()
+		.chain(std::iter::once(&model.target_label))
+		.map(|s| s.as_str())
+		.collect();
+	if let Some(label) = labels
+		.iter()
+		.find(|l| l.is_empty() || l.contains(char::is_whitespace))
+	{
+		return Err(Error::new(
+			ErrorKind::InvalidInput,
+			format!(
+				"label {:?} can't be stored in the legacy format, use json or binary",
+				label
+			),
+		));
+	}
+	let mut file = File::create(path)?;
+	let theta: Vec<String> = model.theta.iter().map(|t| t.to_string()).collect();
+	let out = format!("{}\n{}\n", labels.join(" "), theta.join(" "));
+	file.write_all(out.as_bytes())?;
+	Ok(())
+}
+
+/// Saves the model in given format, legacy and binary formats drop the metadata.
+pub fn save_as(saved: &SavedModel, path: &str, format: Format) -> Result<()> {
+	match format {
+		Format::Json => fs::write(path, format!("{}\n", to_json(saved))),
+		Format::Legacy => save(&saved.model, path),
+		Format::Binary => fs::write(path, to_binary(&saved.model)),
+	}
+}
+
+/// Loads model from a file of any format, see [`load_saved`]
+pub fn load(filename: &str) -> Result<LinearModel> {
+	Ok(load_saved(filename)?.model)
+}
+
+/// Loads model with its metadata, detecting the format by content.
+///
+/// Malformed JSON and legacy files are reported as [`ErrorKind::InvalidData`], malformed binary
+/// as [`BinaryError`].
+pub fn load_saved(filename: &str) -> Result<SavedModel> {
+	let bytes = fs::read(filename)?;
+	if bytes.starts_with(MAGIC) {
+		return Ok(SavedModel {
+			model: from_binary(&bytes).map_err(Error::from)?,
+			metadata: Metadata::default(),
+		});
+	}
+	let contents = String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+	match contents.trim_start().starts_with('{') {
+		true => from_json(&contents).map_err(|e| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!("{}: invalid model file, {}", filename, e),
+			)
+		}),
+		false => Ok(SavedModel {
+			model: parse_legacy(&contents).map_err(|e| {
+				Error::new(
+					ErrorKind::InvalidData,
+					format!("{}: invalid legacy model file, {}", filename, e),
+				)
+			})?,
+			metadata: Metadata::default(),
+		}),
+	}
+}
+
+/// Magic header of the binary format
+const MAGIC: &[u8] = b"FTLR";
+
+/// Version of the binary format
+pub const BINARY_VERSION: u16 = 1;
+
+/// Rejection reason of a binary model file
+///
+/// Returned inside [`std::io::Error`], truncated files are [`ErrorKind::UnexpectedEof`],
+/// the others [`ErrorKind::InvalidData`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+	/// File doesn't start with the magic header
+	Magic,
+	/// File was written by newer version
+	Version(u16),
+	/// File ends before the content does
+	Truncated,
+	/// Content is followed by unexpected bytes
+	TrailingBytes(usize),
+	/// Label is not valid UTF-8
+	Label,
+	/// Checksum doesn't match the content
+	Checksum { stored: u32, computed: u32 },
+}
+
+impl fmt::Display for BinaryError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BinaryError::Magic => write!(f, "Not a binary model file"),
+			BinaryError::Version(v) => write!(
+				f,
+				"Binary model version {} is newer than supported {}",
+				v, BINARY_VERSION
+			),
+			BinaryError::Truncated => write!(f, "Binary model file is truncated"),
+			BinaryError::TrailingBytes(n) => {
+				write!(f, "Binary model file has {} unexpected trailing bytes", n)
+			}
+			BinaryError::Label => write!(f, "Binary model label is not valid UTF-8"),
+			BinaryError::Checksum { stored, computed } => write!(
+				f,
+				"Binary model file is corrupted, checksum {:08x} doesn't match content {:08x}",
+				stored, computed
+			),
+		}
+	}
+}
+
+impl std::error::Error for BinaryError {}
+
+impl From<BinaryError> for Error {
+	fn from(e: BinaryError) -> Self {
+		match e {
+			BinaryError::Truncated => Error::new(ErrorKind::UnexpectedEof, e),
+			_ => Error::new(ErrorKind::InvalidData, e),
+		}
+	}
+}
+
+/// CRC-32 (IEEE 802.3) lookup table
+const CRC_TABLE: [u32; 256] = {
+	let mut table = [0; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = match crc & 1 {
+				1 => 0xedb88320 ^ (crc >> 1),
+				_ => crc >> 1,
+			};
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+};
+
+/// CRC-32 checksum as used by zip and PNG
+pub fn crc32(bytes: &[u8]) -> u32 {
+	!bytes.iter().fold(!0, |crc, b| {
+		CRC_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+	})
+}
+
+fn to_binary(model: &LinearModel) -> Vec<u8> {
+	let mut out = MAGIC.to_vec();
+	out.extend(BINARY_VERSION.to_le_bytes());
+	out.extend((model.feature_count() as u32).to_le_bytes());
+	for label in model
+		.feature_labels
+		.iter()
+		.chain(std::iter::once(&model.target_label))
+	{
+		out.extend((label.len() as u32).to_le_bytes());
+		out.extend(label.as_bytes());
+	}
+	for t in model.theta.iter() {
+		out.extend(t.to_le_bytes());
+	}
+	out.extend(crc32(&out).to_le_bytes());
+	out
+}
+
+/// Cursor over the binary content
+struct Reader<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, n: usize) -> std::result::Result<&'a [u8], BinaryError> {
+		let end = self.pos.checked_add(n).ok_or(BinaryError::Truncated)?;
+		let taken = self
+			.bytes
+			.get(self.pos..end)
+			.ok_or(BinaryError::Truncated)?;
+		self.pos = end;
+		Ok(taken)
+	}
+
+	fn array<const N: usize>(&mut self) -> std::result::Result<[u8; N], BinaryError> {
+		Ok(self.take(N)?.try_into().unwrap())
+	}
+
+	fn label(&mut self) -> std::result::Result<String, BinaryError> {
+		let len = u32::from_le_bytes(self.array()?) as usize;
+		String::from_utf8(self.take(len)?.to_vec()).map_err(|_| BinaryError::Label)
+	}
+}
+
+fn from_binary(bytes: &[u8]) -> std::result::Result<LinearModel, BinaryError> {
+	let mut reader = Reader { bytes, pos: 0 };
+	if reader.take(MAGIC.len())? != MAGIC {
+		return Err(BinaryError::Magic);
+	}
+	let version = u16::from_le_bytes(reader.array()?);
+	if version > BINARY_VERSION {
+		return Err(BinaryError::Version(version));
+	}
+	let feature_count = u32::from_le_bytes(reader.array()?) as usize;
+	// Every label takes at least its length, so larger counts can't fit
+	if feature_count > bytes.len() / 4 {
+		return Err(BinaryError::Truncated);
+	}
+	let feature_labels = (0..feature_count)
+		.map(|_| reader.label())
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	let target_label = reader.label()?;
+	let theta = (0..=feature_count)
+		.map(|_| Ok(f64::from_le_bytes(reader.array()?)))
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	let content = reader.pos;
+	let stored = u32::from_le_bytes(reader.array()?);
+	if reader.pos != bytes.len() {
+		return Err(BinaryError::TrailingBytes(bytes.len() - reader.pos));
+	}
+	let computed = crc32(&bytes[..content]);
+	if stored != computed {
+		return Err(BinaryError::Checksum { stored, computed });
+	}
+	Ok(LinearModel::new(feature_labels, target_label, theta))
+}
+
+fn parse_legacy(contents: &str) -> std::result::Result<LinearModel, String> {
+	let mut lines = contents.lines();
+	// Take first line and split it. Assign labels.
+	let mut labels: Vec<String> = lines
+		.next()
+		.ok_or("missing labels line")?
+		.split_whitespace()
+		.map(|s| s.to_string())
+		.collect();
+	// Take second line and split it. Assign thetas.
+	let theta: Vec<f64> = lines
+		.next()
+		.ok_or("missing theta line")?
+		.split_whitespace()
+		.map(|s| s.parse().map_err(|_| format!("invalid theta {:?}", s)))
+		.collect::<std::result::Result<_, _>>()?;
+	if theta.len() < 2 || labels.len() != theta.len() {
+		return Err(format!(
+			"{} labels don't match {} theta values",
+			labels.len(),
+			theta.len()
+		));
+	}
+	let target_label = labels.pop().unwrap();
+	Ok(LinearModel::new(labels, target_label, theta))
+}
+
+/// Current time in RFC 3339 format, UTC
+pub fn timestamp() -> String {
+	let secs = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs());
+	// Civil date from days since epoch, Howard Hinnant's algorithm
+	let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + (month <= 2) as i64;
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+		year,
+		month,
+		day,
+		rem / 3600,
+		rem % 3600 / 60,
+		rem % 60
+	)
+}
+
+fn string(s: &str) -> Value {
+	Value::String(s.to_string())
+}
+
+fn scaling_to_json(scaling: &Scaling) -> Value {
+	Value::Object(vec![
+		(String::from("method"), string(&scaling.method.to_string())),
+		(String::from("offset"), Value::number(scaling.offset)),
+		(String::from("scale"), Value::number(scaling.scale)),
+	])
+}
+
+fn to_json(saved: &SavedModel) -> Value {
+	let (model, meta) = (&saved.model, &saved.metadata);
+	let scaler = meta.scaling.as_ref().map_or(Value::Null, |scaling| {
+		Value::Object(vec![
+			(
+				String::from("features"),
+				Value::Array(scaling.features.iter().map(scaling_to_json).collect()),
+			),
+			(String::from("target"), scaling_to_json(&scaling.target)),
+		])
+	});
+	let inference = meta.inference.as_ref().map_or(Value::Null, |inference| {
+		let covariance = &inference.covariance;
+		Value::Object(vec![
+			(
+				String::from("residual_se"),
+				Value::number(inference.residual_se),
+			),
+			(String::from("n"), Value::Number(inference.n.to_string())),
+			(
+				String::from("covariance"),
+				Value::Array(
+					(0..covariance.rows())
+						.map(|i| {
+							Value::Array(
+								covariance
+									.row(i)
+									.iter()
+									.map(|v| Value::number(*v))
+									.collect(),
+							)
+						})
+						.collect(),
+				),
+			),
+		])
+	});
+	let domain = meta.domain.as_ref().map_or(Value::Null, |domain| {
+		Value::Array(
+			domain
+				.features
+				.iter()
+				.map(|range| {
+					Value::Object(vec![
+						(String::from("min"), Value::number(range.min)),
+						(String::from("max"), Value::number(range.max)),
+						(
+							String::from("quantiles"),
+							Value::Object(
+								range
+									.quantiles
+									.iter()
+									.map(|(q, v)| (q.to_string(), Value::number(*v)))
+									.collect(),
+							),
+						),
+					])
+				})
+				.collect(),
+		)
+	});
+	let numbers = |values: &[f64]| Value::Array(values.iter().map(|v| Value::number(*v)).collect());
+	let path = meta.path.as_ref().map_or(Value::Null, |path| {
+		Value::Object(vec![
+			(String::from("rule"), string(&path.rule.to_string())),
+			(String::from("lambda_min"), Value::number(path.lambda_min)),
+			(String::from("lambda_1se"), Value::number(path.lambda_1se)),
+			(String::from("lambdas"), numbers(&path.lambdas)),
+			(String::from("cv_mse"), numbers(&path.cv_mse)),
+			(String::from("cv_se"), numbers(&path.cv_se)),
+			(
+				String::from("coefficients"),
+				Value::Array(path.coefficients.iter().map(|t| numbers(t)).collect()),
+			),
+		])
+	});
+	let optional = |value: Option<Value>| value.unwrap_or(Value::Null);
+	Value::Object(vec![
+		(
+			String::from("format_version"),
+			Value::Number(FORMAT_VERSION.to_string()),
+		),
+		(String::from("model_type"), string(MODEL_TYPE)),
+		(
+			String::from("features"),
+			Value::Array(model.feature_labels.iter().map(|l| string(l)).collect()),
+		),
+		(String::from("target"), string(&model.target_label)),
+		(
+			String::from("coefficients"),
+			Value::Array(model.theta.iter().map(|t| Value::number(*t)).collect()),
+		),
+		(String::from("scaler"), scaler),
+		(String::from("inference"), inference),
+		(String::from("domain"), domain),
+		(String::from("regularization_path"), path),
+		(
+			String::from("hyperparameters"),
+			Value::Object(
+				meta.hyperparameters
+					.iter()
+					.map(|(k, v)| (k.clone(), string(v)))
+					.collect(),
+			),
+		),
+		(
+			String::from("seed"),
+			optional(meta.seed.map(|s| Value::Number(s.to_string()))),
+		),
+		(
+			String::from("dataset_hash"),
+			optional(meta.dataset_hash.map(|h| string(&format!("{:016x}", h)))),
+		),
+		(
+			String::from("metrics"),
+			Value::Object(
+				meta.metrics
+					.iter()
+					.map(|(k, v)| (k.clone(), Value::number(*v)))
+					.collect(),
+			),
+		),
+		(
+			String::from("timestamp"),
+			optional(meta.timestamp.as_deref().map(string)),
+		),
+	])
+}
+
+/// Member of the JSON object, `null` and missing members are `Option::None`
+fn field<'a>(json: &'a Value, key: &str) -> Option<&'a Value> {
+	json.get(key).filter(|v| **v != Value::Null)
+}
+
+fn required<'a>(json: &'a Value, key: &str) -> std::result::Result<&'a Value, String> {
+	field(json, key).ok_or_else(|| format!("missing {:?}", key))
+}
+
+fn invalid(key: &str) -> String {
+	format!("invalid {:?}", key)
+}
+
+fn number(json: &Value, key: &str) -> std::result::Result<f64, String> {
+	required(json, key)?.as_f64().ok_or_else(|| invalid(key))
+}
+
+fn scaling_from_json(json: &Value) -> std::result::Result<Scaling, String> {
+	Ok(Scaling {
+		method: required(json, "method")?
+			.as_str()
+			.and_then(|s| s.parse().ok())
+			.ok_or_else(|| invalid("method"))?,
+		offset: number(json, "offset")?,
+		scale: number(json, "scale")?,
+	})
+}
+
+fn from_json(contents: &str) -> std::result::Result<SavedModel, String> {
+	let json = Value::parse(contents)?;
+	let version = required(&json, "format_version")?
+		.as_u64()
+		.ok_or_else(|| invalid("format_version"))?;
+	if version > FORMAT_VERSION {
+		return Err(format!(
+			"format version {} is newer than supported {}",
+			version, FORMAT_VERSION
+		));
+	}
+	match required(&json, "model_type")?.as_str() {
+		Some(MODEL_TYPE) => {}
+		_ => return Err(invalid("model_type")),
+	}
+	let feature_labels: Vec<String> = required(&json, "features")?
+		.as_array()
+		.and_then(|a| a.iter().map(|v| v.as_str().map(String::from)).collect())
+		.ok_or_else(|| invalid("features"))?;
+	let target_label = required(&json, "target")?
+		.as_str()
+		.ok_or_else(|| invalid("target"))?
+		.to_string();
+	let theta: Vec<f64> = required(&json, "coefficients")?
+		.as_array()
+		.and_then(|a| a.iter().map(|v| v.as_f64()).collect())
+		.ok_or_else(|| invalid("coefficients"))?;
+	if theta.len() != feature_labels.len() + 1 {
+		return Err(String::from("coefficients don't match features"));
+	}
+	let scaling = match field(&json, "scaler") {
+		None => None,
+		Some(scaler) => Some(SampleScaling {
+			features: required(scaler, "features")?
+				.as_array()
+				.ok_or_else(|| invalid("features"))?
+				.iter()
+				.map(scaling_from_json)
+				.collect::<std::result::Result<_, _>>()?,
+			target: scaling_from_json(required(scaler, "target")?)?,
+		}),
+	};
+	let inference = match field(&json, "inference") {
+		None => None,
+		Some(inference) => {
+			let rows: Vec<Vec<f64>> = required(inference, "covariance")?
+				.as_array()
+				.and_then(|rows| {
+					rows.iter()
+						.map(|row| row.as_array()?.iter().map(|v| v.as_f64()).collect())
+						.collect()
+				})
+				.ok_or_else(|| invalid("covariance"))?;
+			if rows.len() != theta.len() || rows.iter().any(|row| row.len() != theta.len()) {
+				return Err(String::from("covariance doesn't match coefficients"));
+			}
+			Some(Inference {
+				residual_se: number(inference, "residual_se")?,
+				n: required(inference, "n")?
+					.as_u64()
+					.ok_or_else(|| invalid("n"))? as usize,
+				covariance: Matrix::from_rows(&rows),
+			})
+		}
+	};
+	let domain = match field(&json, "domain") {
+		None => None,
+		Some(domain) => {
+			let features = domain
+				.as_array()
+				.ok_or_else(|| invalid("domain"))?
+				.iter()
+				.map(|range| {
+					Ok(FeatureRange {
+						min: number(range, "min")?,
+						max: number(range, "max")?,
+						quantiles: field(range, "quantiles")
+							.map_or(Some(&[][..]), |v| v.as_object())
+							.and_then(|q| {
+								q.iter()
+									.map(|(k, v)| Some((k.parse().ok()?, v.as_f64()?)))
+									.collect()
+							})
+							.ok_or_else(|| invalid("quantiles"))?,
+					})
+				})
+				.collect::<std::result::Result<Vec<_>, String>>()?;
+			if features.len() != feature_labels.len() {
+				return Err(String::from("domain doesn't match features"));
+			}
+			Some(Domain { features })
+		}
+	};
+	let path = match field(&json, "regularization_path") {
+		None => None,
+		Some(path) => {
+			let numbers = |key: &str| {
+				required(path, key)?
+					.as_array()
+					.and_then(|a| a.iter().map(|v| v.as_f64()).collect::<Option<Vec<f64>>>())
+					.ok_or_else(|| invalid(key))
+			};
+			let lambdas = numbers("lambdas")?;
+			let (cv_mse, cv_se) = (numbers("cv_mse")?, numbers("cv_se")?);
+			let coefficients: Vec<Vec<f64>> = required(path, "coefficients")?
+				.as_array()
+				.and_then(|rows| {
+					rows.iter()
+						.map(|row| row.as_array()?.iter().map(|v| v.as_f64()).collect())
+						.collect()
+				})
+				.ok_or_else(|| invalid("coefficients"))?;
+			if cv_mse.len() != lambdas.len()
+				|| cv_se.len() != lambdas.len()
+				|| coefficients.len() != lambdas.len()
+				|| coefficients.iter().any(|t| t.len() != theta.len())
+			{
+				return Err(String::from(
+					"regularization path doesn't match coefficients",
+				));
+			}
+			Some(RegularizationPath {
+				lambdas,
+				coefficients,
+				cv_mse,
+				cv_se,
+				lambda_min: number(path, "lambda_min")?,
+				lambda_1se: number(path, "lambda_1se")?,
+				rule: required(path, "rule")?
+					.as_str()
+					.and_then(|s| s.parse::<LambdaRule>().ok())
+					.ok_or_else(|| invalid("rule"))?,
+			})
+		}
+	};
+	let pairs = |key: &str| field(&json, key).map_or(Some(&[][..]), |v| v.as_object());
+	let hyperparameters = pairs("hyperparameters")
+		.and_then(|p| {
+			p.iter()
+				.map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+				.collect()
+		})
+		.ok_or_else(|| invalid("hyperparameters"))?;
+	let metrics = pairs("metrics")
+		.and_then(|p| {
+			p.iter()
+				.map(|(k, v)| v.as_f64().map(|v| (k.clone(), v)))
+				.collect()
+		})
+		.ok_or_else(|| invalid("metrics"))?;
+	let metadata = Metadata {
+		scaling,
+		inference,
+		domain,
+		path,
+		hyperparameters,
+		seed: match field(&json, "seed") {
+			None => None,
+			Some(seed) => Some(seed.as_u64().ok_or_else(|| invalid("seed"))?),
+		},
+		dataset_hash: match field(&json, "dataset_hash") {
+			None => None,
+			Some(hash) => Some(
+				hash.as_str()
+					.and_then(|h| u64::from_str_radix(h, 16).ok())
+					.ok_or_else(|| invalid("dataset_hash"))?,
+			),
+		},
+		metrics,
+		timestamp: match field(&json, "timestamp") {
+			None => None,
+			Some(t) => Some(t.as_str().ok_or_else(|| invalid("timestamp"))?.to_string()),
+		},
+	};
+	Ok(SavedModel {
+		model: LinearModel::new(feature_labels, target_label, theta),
+		metadata,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::scaler::Scaler;
+
+	fn saved() -> SavedModel {
+		SavedModel {
+			model: LinearModel::new(
+				vec![String::from("engine size"), String::from("\"age\"")],
+				String::from("price [€]"),
+				vec![8499.599649933216, -0.0214489635917023, 1e-30],
+			),
+			metadata: Metadata {
+				scaling: Some(SampleScaling {
+					features: vec![
+						Scaler::MinMax.fit(&[1.0, 3.0]),
+						Scaler::ZScore.fit(&[1.0, 3.0]),
+					],
+					target: Scaling::IDENTITY,
+				}),
+				hyperparameters: vec![(String::from("solver"), String::from("gd"))],
+				seed: Some(u64::MAX),
+				dataset_hash: Some(0x0123456789abcdef),
+				metrics: vec![(String::from("test_r2"), 0.125)],
+				inference: Some(Inference {
+					residual_se: 512.25,
+					n: 24,
+					covariance: Matrix::from_rows(&[
+						vec![0.5, -1e-6, 0.0],
+						vec![-1e-6, 2e-12, 0.0],
+						vec![0.0, 0.0, 1.0],
+					]),
+				}),
+				domain: Some(Domain {
+					features: vec![
+						FeatureRange {
+							min: 1.0,
+							max: 3.0,
+							quantiles: vec![(0.5, 2.0)],
+						},
+						FeatureRange {
+							min: -0.5,
+							max: 1e9,
+							quantiles: vec![],
+						},
+					],
+				}),
+				path: Some(RegularizationPath {
+					lambdas: vec![10.0, 1.0],
+					coefficients: vec![vec![8000.0, 0.0, 0.0], vec![8400.0, -0.02, 1e-31]],
+					cv_mse: vec![2e6, 5e5],
+					cv_se: vec![1e5, 2.5e4],
+					lambda_min: 1.0,
+					lambda_1se: 1.0,
+					rule: LambdaRule::OneSe,
+				}),
+				timestamp: Some(timestamp()),
+			},
+		}
+	}
+
+	#[test]
+	fn json_roundtrip() {
+		let saved = saved();
+		assert_eq!(from_json(&to_json(&saved).to_string()), Ok(saved));
+		let minimal = r#"{"format_version": 1, "model_type": "linear_regression",
+			"features": ["x"], "target": "y", "coefficients": [1, 2]}"#;
+		let minimal = from_json(minimal).unwrap();
+		assert_eq!(minimal.model.theta, vec![1.0, 2.0]);
+		assert_eq!(minimal.metadata, Metadata::default());
+	}
+
+	#[test]
+	fn json_errors() {
+		let json = to_json(&saved()).to_string();
+		assert!(
+			from_json(&json.replace("\"format_version\": 1", "\"format_version\": 2")).is_err()
+		);
+		assert!(from_json(&json.replace("linear_regression", "tree")).is_err());
+		assert!(from_json(&json.replace("1e-30", "")).is_err());
+		assert!(from_json(&json[..json.len() / 2]).is_err());
+		let ragged = json.replace("1.0\n\t\t\t]", "1.0,\n1.0\n\t\t\t]");
+		assert_ne!(ragged, json);
+		assert!(from_json(&ragged).is_err());
+		let short_path = json.replace("1e-31", "");
+		assert_ne!(short_path, json);
+		assert!(from_json(&short_path).is_err());
+	}
+
+	#[test]
+	fn binary() {
+		let model = saved().model;
+		let bytes = to_binary(&model);
+		assert_eq!(from_binary(&bytes), Ok(model));
+		assert_eq!(crc32(b"123456789"), 0xcbf43926);
+		for len in [0, 5, 12, bytes.len() - 1] {
+			assert_eq!(from_binary(&bytes[..len]), Err(BinaryError::Truncated));
+		}
+		let mut corrupted = bytes.clone();
+		corrupted[20] ^= 0x10;
+		assert!(matches!(
+			from_binary(&corrupted),
+			Err(BinaryError::Checksum { .. })
+		));
+		let mut newer = bytes.clone();
+		newer[4] = 2;
+		assert_eq!(from_binary(&newer), Err(BinaryError::Version(2)));
+		let mut trailing = bytes.clone();
+		trailing.push(0);
+		assert_eq!(from_binary(&trailing), Err(BinaryError::TrailingBytes(1)));
+	}
+
+	#[test]
+	fn legacy_errors() {
+		assert_eq!(
+			parse_legacy("km price\n8115.67 -0.0222\n").unwrap().theta,
+			vec![8115.67, -0.0222]
+		);
+		let path = std::env::temp_dir().join("ft_linear_regression_legacy_errors");
+		let path = path.to_str().unwrap();
+		for contents in [
+			"",
+			"km price\n",
+			"km price\n8115.67 x\n",
+			"km price\n8115.67\n",
+		] {
+			fs::write(path, contents).unwrap();
+			let err = load_saved(path).unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidData, "{:?}", contents);
+		}
+		let spaced = LinearModel::new(
+			vec![String::from("distance driven")],
+			String::from("price"),
+			vec![1.0, 2.0],
+		);
+		let err = save(&spaced, path).unwrap_err();
+		assert_eq!(err.kind(), ErrorKind::InvalidInput);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn timestamp_format() {
+		let t = timestamp();
+		assert_eq!(t.len(), 20);
+		assert!(t.starts_with("20") && t.ends_with('Z'));
+	}
+}
@@ -0,0 +1,534 @@
+//! Optimizers used to fit the linear regression model
+//!
+//! Uses the vectorized form of training formulas:
+//! - `tmp_theta[j] = learningRate * (1 / m) * Sum(i=0; m-1)(estimate(x[i]) - y[i]) * x[i][j]`
+//! - where m is length of the batch and `x[i][0] == 1` for the intercept
+//! - weighted samples count `w[i]` times, `m` is then the sum of the weights
+//!
+//! The batch is the whole training set, single sample (SGD) or a mini-batch, see [`Batching`].
+//! How the gradient turns into the theta update is up to the [`Optimizer`], the cost and
+//! its gradient, squared error by default, are computed by [`loss`](crate::loss).
+use std::fmt;
+use std::str::FromStr;
+
+use crate::params::Spec;
+
+/// How many samples are used for a single theta update
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Batching {
+	/// Whole training set, one update per epoch
+	#[default]
+	Full,
+	/// Single sample per update
+	Stochastic,
+	/// Given number of samples per update
+	MiniBatch(usize),
+}
+
+impl Batching {
+	/// Number of samples in a batch for training set of length `m`, at least 1
+	pub fn batch_size(&self, m: usize) -> usize {
+		match self {
+			Batching::Full => m.max(1),
+			Batching::Stochastic => 1,
+			Batching::MiniBatch(size) => (*size).clamp(1, m.max(1)),
+		}
+	}
+
+	/// Whether the training set is reshuffled every epoch
+	pub fn shuffles(&self) -> bool {
+		*self != Batching::Full
+	}
+}
+
+/// Accepts `full`, `sgd` and `mini-batch`, which uses batches of 32 samples
+impl FromStr for Batching {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"full" => Ok(Batching::Full),
+			"sgd" => Ok(Batching::Stochastic),
+			"mini-batch" => Ok(Batching::MiniBatch(32)),
+			_ => Err(format!("Unknown batching {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Batching {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Batching::Full => write!(f, "full"),
+			Batching::Stochastic => write!(f, "sgd"),
+			Batching::MiniBatch(size) => write!(f, "mini-batch of {}", size),
+		}
+	}
+}
+
+/// Reaction of the gradient descent to a rising or non-finite cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Divergence {
+	/// Stop the training with an error
+	#[default]
+	Abort,
+	/// Roll theta back to the previous epoch and retry with halved learning rate
+	Backoff,
+}
+
+/// Accepts `abort` and `backoff`
+impl FromStr for Divergence {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"abort" => Ok(Divergence::Abort),
+			"backoff" => Ok(Divergence::Backoff),
+			_ => Err(format!("Unknown divergence policy {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Divergence {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Divergence::Abort => write!(f, "abort"),
+			Divergence::Backoff => write!(f, "backoff"),
+		}
+	}
+}
+
+/// Theta update rule
+///
+/// Optimizers may keep state between steps (e.g. velocity), so a fresh
+/// instance should be used for every training run, see [`OptimizerKind::build`].
+pub trait Optimizer: fmt::Debug {
+	/// Updates `theta` using the `gradient` of the cost function on the current batch.
+	///
+	/// Returns the temporal difference (the change applied to theta).
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64>;
+}
+
+/// Subtracts the temporal difference from theta and returns it
+fn apply(theta: &mut [f64], temp_diff: Vec<f64>) -> Vec<f64> {
+	for (t, d) in theta.iter_mut().zip(temp_diff.iter()) {
+		*t -= d;
+	}
+	temp_diff
+}
+
+/// Resets the optimizer state on first use
+fn init_state(state: &mut Vec<f64>, len: usize) {
+	if state.len() != len {
+		*state = vec![0.0; len];
+	}
+}
+
+/// Plain gradient descent, `theta -= alpha * gradient`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GradientDescent;
+
+impl Optimizer for GradientDescent {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		let temp_diff = gradient(theta).iter().map(|g| learning_rate * g).collect();
+		apply(theta, temp_diff)
+	}
+}
+
+/// Gradient descent with momentum
+///
+/// `v = beta * v + alpha * gradient`, `theta -= v`
+#[derive(Debug, Clone, Default)]
+pub struct Momentum {
+	/// Fraction of the previous update kept
+	pub beta: f64,
+	velocity: Vec<f64>,
+}
+
+impl Momentum {
+	pub fn new(beta: f64) -> Self {
+		Self {
+			beta,
+			velocity: Vec::new(),
+		}
+	}
+}
+
+impl Optimizer for Momentum {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		init_state(&mut self.velocity, theta.len());
+		for (v, g) in self.velocity.iter_mut().zip(gradient(theta).iter()) {
+			*v = self.beta * *v + learning_rate * g;
+		}
+		apply(theta, self.velocity.clone())
+	}
+}
+
+/// Nesterov accelerated gradient
+///
+/// Same as [`Momentum`], but the gradient is evaluated at the look-ahead point `theta - beta * v`.
+#[derive(Debug, Clone, Default)]
+pub struct Nesterov {
+	/// Fraction of the previous update kept
+	pub beta: f64,
+	velocity: Vec<f64>,
+}
+
+impl Nesterov {
+	pub fn new(beta: f64) -> Self {
+		Self {
+			beta,
+			velocity: Vec::new(),
+		}
+	}
+}
+
+impl Optimizer for Nesterov {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		init_state(&mut self.velocity, theta.len());
+		let look_ahead: Vec<f64> = theta
+			.iter()
+			.zip(self.velocity.iter())
+			.map(|(t, v)| t - self.beta * v)
+			.collect();
+		for (v, g) in self.velocity.iter_mut().zip(gradient(&look_ahead).iter()) {
+			*v = self.beta * *v + learning_rate * g;
+		}
+		apply(theta, self.velocity.clone())
+	}
+}
+
+/// Adaptive gradient, per-parameter learning rate scaled by all past squared gradients
+///
+/// `G += gradient^2`, `theta -= alpha * gradient / (sqrt(G) + epsilon)`
+#[derive(Debug, Clone, Default)]
+pub struct AdaGrad {
+	/// Numerical stability term
+	pub epsilon: f64,
+	sum_sq: Vec<f64>,
+}
+
+impl AdaGrad {
+	pub fn new(epsilon: f64) -> Self {
+		Self {
+			epsilon,
+			sum_sq: Vec::new(),
+		}
+	}
+}
+
+impl Optimizer for AdaGrad {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		init_state(&mut self.sum_sq, theta.len());
+		let temp_diff = gradient(theta)
+			.iter()
+			.zip(self.sum_sq.iter_mut())
+			.map(|(g, s)| {
+				*s += g * g;
+				learning_rate * g / (s.sqrt() + self.epsilon)
+			})
+			.collect();
+		apply(theta, temp_diff)
+	}
+}
+
+/// Root mean square propagation, AdaGrad with exponentially decaying average
+///
+/// `E = rho * E + (1 - rho) * gradient^2`, `theta -= alpha * gradient / (sqrt(E) + epsilon)`
+#[derive(Debug, Clone, Default)]
+pub struct RmsProp {
+	/// Decay rate of the squared gradient average
+	pub rho: f64,
+	/// Numerical stability term
+	pub epsilon: f64,
+	avg_sq: Vec<f64>,
+}
+
+impl RmsProp {
+	pub fn new(rho: f64, epsilon: f64) -> Self {
+		Self {
+			rho,
+			epsilon,
+			avg_sq: Vec::new(),
+		}
+	}
+}
+
+impl Optimizer for RmsProp {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		init_state(&mut self.avg_sq, theta.len());
+		let temp_diff = gradient(theta)
+			.iter()
+			.zip(self.avg_sq.iter_mut())
+			.map(|(g, e)| {
+				*e = self.rho * *e + (1.0 - self.rho) * g * g;
+				learning_rate * g / (e.sqrt() + self.epsilon)
+			})
+			.collect();
+		apply(theta, temp_diff)
+	}
+}
+
+/// Adaptive moment estimation
+///
+/// Bias corrected moving averages of the gradient `m` and squared gradient `v`,
+/// `theta -= alpha * m / (sqrt(v) + epsilon)`
+#[derive(Debug, Clone, Default)]
+pub struct Adam {
+	/// Decay rate of the gradient average
+	pub beta1: f64,
+	/// Decay rate of the squared gradient average
+	pub beta2: f64,
+	/// Numerical stability term
+	pub epsilon: f64,
+	m: Vec<f64>,
+	v: Vec<f64>,
+	t: i32,
+}
+
+impl Adam {
+	pub fn new(beta1: f64, beta2: f64, epsilon: f64) -> Self {
+		Self {
+			beta1,
+			beta2,
+			epsilon,
+			..Self::default()
+		}
+	}
+}
+
+impl Optimizer for Adam {
+	fn step(
+		&mut self,
+		theta: &mut [f64],
+		gradient: &dyn Fn(&[f64]) -> Vec<f64>,
+		learning_rate: f64,
+	) -> Vec<f64> {
+		init_state(&mut self.m, theta.len());
+		init_state(&mut self.v, theta.len());
+		self.t += 1;
+		let m_correction = 1.0 - self.beta1.powi(self.t);
+		let v_correction = 1.0 - self.beta2.powi(self.t);
+		let temp_diff = gradient(theta)
+			.iter()
+			.zip(self.m.iter_mut().zip(self.v.iter_mut()))
+			.map(|(g, (m, v))| {
+				*m = self.beta1 * *m + (1.0 - self.beta1) * g;
+				*v = self.beta2 * *v + (1.0 - self.beta2) * g * g;
+				learning_rate * (*m / m_correction) / ((*v / v_correction).sqrt() + self.epsilon)
+			})
+			.collect();
+		apply(theta, temp_diff)
+	}
+}
+
+/// Optimizer selection with its hyperparameters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OptimizerKind {
+	#[default]
+	GradientDescent,
+	Momentum {
+		beta: f64,
+	},
+	Nesterov {
+		beta: f64,
+	},
+	AdaGrad {
+		epsilon: f64,
+	},
+	RmsProp {
+		rho: f64,
+		epsilon: f64,
+	},
+	Adam {
+		beta1: f64,
+		beta2: f64,
+		epsilon: f64,
+	},
+}
+
+impl OptimizerKind {
+	/// Creates a fresh optimizer
+	pub fn build(&self) -> Box<dyn Optimizer> {
+		match *self {
+			OptimizerKind::GradientDescent => Box::new(GradientDescent),
+			OptimizerKind::Momentum { beta } => Box::new(Momentum::new(beta)),
+			OptimizerKind::Nesterov { beta } => Box::new(Nesterov::new(beta)),
+			OptimizerKind::AdaGrad { epsilon } => Box::new(AdaGrad::new(epsilon)),
+			OptimizerKind::RmsProp { rho, epsilon } => Box::new(RmsProp::new(rho, epsilon)),
+			OptimizerKind::Adam {
+				beta1,
+				beta2,
+				epsilon,
+			} => Box::new(Adam::new(beta1, beta2, epsilon)),
+		}
+	}
+}
+
+/// Decay rate parameter, in `[0, 1)`
+fn decay<'a>(spec: &Spec<'a>, key: &'a str, default: f64) -> Result<f64, String> {
+	match spec.get(key, default) {
+		value if (0.0..1.0).contains(&value) => Ok(value),
+		value => Err(format!(
+			"Invalid {} {} {}, expected 0 to 1 (exclusive)",
+			spec.name, key, value
+		)),
+	}
+}
+
+/// Denominator offset parameter, above 0
+fn epsilon(spec: &Spec) -> Result<f64, String> {
+	match spec.get("epsilon", 1e-8) {
+		value if value > 0.0 && value.is_finite() => Ok(value),
+		value => Err(format!(
+			"Invalid {} epsilon {}, expected above 0",
+			spec.name, value
+		)),
+	}
+}
+
+/// Accepts `name[:param=value,...]`, e.g. `adam` or `adam:beta1=0.8,beta2=0.99`
+///
+/// Names are `gd`, `momentum`, `nesterov`, `adagrad`, `rmsprop` and `adam`.
+/// Omitted parameters take the common defaults, `beta = 0.9`, `rho = 0.9`,
+/// `beta1 = 0.9`, `beta2 = 0.999` and `epsilon = 1e-8`. Decay rates `beta` and `rho`
+/// must be in `[0, 1)`, `epsilon` above 0.
+impl FromStr for OptimizerKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let spec = Spec::parse(s)?;
+		let kind = match spec.name {
+			"gd" => OptimizerKind::GradientDescent,
+			"momentum" => OptimizerKind::Momentum {
+				beta: decay(&spec, "beta", 0.9)?,
+			},
+			"nesterov" => OptimizerKind::Nesterov {
+				beta: decay(&spec, "beta", 0.9)?,
+			},
+			"adagrad" => OptimizerKind::AdaGrad {
+				epsilon: epsilon(&spec)?,
+			},
+			"rmsprop" => OptimizerKind::RmsProp {
+				rho: decay(&spec, "rho", 0.9)?,
+				epsilon: epsilon(&spec)?,
+			},
+			"adam" => OptimizerKind::Adam {
+				beta1: decay(&spec, "beta1", 0.9)?,
+				beta2: decay(&spec, "beta2", 0.999)?,
+				epsilon: epsilon(&spec)?,
+			},
+			_ => return Err(format!("Unknown optimizer {:?}", spec.name)),
+		};
+		spec.finish(kind)
+	}
+}
+
+impl fmt::Display for OptimizerKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			OptimizerKind::GradientDescent => write!(f, "gd"),
+			OptimizerKind::Momentum { beta } => write!(f, "momentum:beta={}", beta),
+			OptimizerKind::Nesterov { beta } => write!(f, "nesterov:beta={}", beta),
+			OptimizerKind::AdaGrad { epsilon } => write!(f, "adagrad:epsilon={}", epsilon),
+			OptimizerKind::RmsProp { rho, epsilon } => {
+				write!(f, "rmsprop:rho={},epsilon={}", rho, epsilon)
+			}
+			OptimizerKind::Adam {
+				beta1,
+				beta2,
+				epsilon,
+			} => write!(
+				f,
+				"adam:beta1={},beta2={},epsilon={}",
+				beta1, beta2, epsilon
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Gradient of `(t0 - 3)^2 + 10 * (t1 + 1)^2`, poorly scaled quadratic
+	fn quadratic(theta: &[f64]) -> Vec<f64> {
+		vec![2.0 * (theta[0] - 3.0), 20.0 * (theta[1] + 1.0)]
+	}
+
+	#[test]
+	fn optimizers_converge() {
+		let kinds = [
+			("gd", 0.05),
+			("momentum", 0.02),
+			("nesterov", 0.02),
+			("adagrad", 1.0),
+			("rmsprop", 0.01),
+			("adam", 0.05),
+		];
+		for (name, alpha) in kinds.iter() {
+			let kind: OptimizerKind = name.parse().unwrap();
+			let mut optimizer = kind.build();
+			let mut theta = vec![0.0, 0.0];
+			for _ in 0..3000 {
+				optimizer.step(&mut theta, &quadratic, *alpha);
+			}
+			assert!(
+				(theta[0] - 3.0).abs() < 1e-2 && (theta[1] + 1.0).abs() < 1e-2,
+				"{} ended at {:?}",
+				name,
+				theta
+			);
+		}
+	}
+
+	#[test]
+	fn parse_kind() {
+		assert_eq!(
+			"adam:beta1=0.8,epsilon=1e-6".parse::<OptimizerKind>(),
+			Ok(OptimizerKind::Adam {
+				beta1: 0.8,
+				beta2: 0.999,
+				epsilon: 1e-6
+			})
+		);
+		let kind = "nesterov:beta=0.5".parse::<OptimizerKind>().unwrap();
+		assert_eq!(kind.to_string().parse::<OptimizerKind>(), Ok(kind));
+		assert!("adam:beta=0.8".parse::<OptimizerKind>().is_err());
+		assert!("momentum:beta=1".parse::<OptimizerKind>().is_err());
+		assert!("rmsprop:rho=-0.1".parse::<OptimizerKind>().is_err());
+		assert!("adam:beta2=1.5".parse::<OptimizerKind>().is_err());
+		assert!("adagrad:epsilon=0".parse::<OptimizerKind>().is_err());
+		assert!("momentum:beta=0".parse::<OptimizerKind>().is_ok());
+		assert!("newton".parse::<OptimizerKind>().is_err());
+	}
+}
@@ -0,0 +1,51 @@
+//! Parsing of `name:param=value,...` specifications used by the command line options
+use std::cell::RefCell;
+
+/// Parsed specification, values are looked up by [`Spec::get`]
+pub(crate) struct Spec<'a> {
+	pub name: &'a str,
+	values: Vec<(&'a str, f64)>,
+	/// Keys looked up so far, to report unknown ones
+	known: RefCell<Vec<&'a str>>,
+}
+
+impl<'a> Spec<'a> {
+	/// Splits the name and numeric parameters
+	pub fn parse(s: &'a str) -> Result<Self, String> {
+		let (name, params) = s.split_once(':').unwrap_or((s, ""));
+		let mut values: Vec<(&str, f64)> = Vec::new();
+		for param in params.split(',').filter(|p| !p.is_empty()) {
+			let (key, value) = param
+				.split_once('=')
+				.ok_or_else(|| format!("Expected param=value, got {:?}", param))?;
+			let value = value
+				.trim()
+				.parse::<f64>()
+				.map_err(|_| format!("Couldn't parse {:?}", param))?;
+			values.push((key.trim(), value));
+		}
+		Ok(Self {
+			name: name.trim(),
+			values,
+			known: RefCell::new(Vec::new()),
+		})
+	}
+
+	/// Parameter value or the default if not given
+	pub fn get(&self, key: &'a str, default: f64) -> f64 {
+		self.known.borrow_mut().push(key);
+		self.values
+			.iter()
+			.find(|(k, _)| *k == key)
+			.map_or(default, |(_, v)| *v)
+	}
+
+	/// Returns the value if all given parameters were looked up
+	pub fn finish<T>(&self, value: T) -> Result<T, String> {
+		let known = self.known.borrow();
+		match self.values.iter().find(|(k, _)| !known.contains(k)) {
+			Some((key, _)) => Err(format!("Unknown parameter {:?} of {}", key, self.name)),
+			None => Ok(value),
+		}
+	}
+}
@@ -0,0 +1,358 @@
+//! Regularization path and the choice of lambda by cross-validation
+//!
+//! The penalized model is fitted for a log-spaced grid of lambda values, from `lambda_max`,
+//! the smallest lambda zeroing all coefficients of the lasso, down to `ratio * lambda_max`.
+//! Every fit starts from the coefficients of the previous lambda (warm start), so the whole
+//! path costs little more than a single fit. The grid is fitted per cross-validation fold
+//! as well and lambda is picked by the mean squared error on the left out folds:
+//! - `lambda_min` has the lowest mean error
+//! - `lambda_1se` is the largest lambda with mean error within one standard error of the
+//!   lowest, the simplest model not distinguishable from the best one
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::loss::total_weight;
+use crate::model::dot;
+use crate::regularization::Regularization;
+use crate::scaler::SampleScaling;
+use crate::solver::coordinate_descent;
+
+/// Smallest share of the L1 penalty used for `lambda_max`, ridge alone never zeroes coefficients
+const MIN_MIXING: f64 = 1e-3;
+
+/// Rule picking lambda from the path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LambdaRule {
+	/// Lowest cross-validated error
+	Min,
+	/// Largest lambda within one standard error of the lowest
+	#[default]
+	OneSe,
+}
+
+/// Accepts `min` and `1se`
+impl FromStr for LambdaRule {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"min" => Ok(LambdaRule::Min),
+			"1se" => Ok(LambdaRule::OneSe),
+			_ => Err(format!("Unknown lambda rule {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for LambdaRule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LambdaRule::Min => write!(f, "min"),
+			LambdaRule::OneSe => write!(f, "1se"),
+		}
+	}
+}
+
+/// Grid of the path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathSettings {
+	/// Number of lambda values
+	pub count: usize,
+	/// Smallest lambda as a fraction of `lambda_max`
+	pub ratio: f64,
+	pub rule: LambdaRule,
+}
+
+impl Default for PathSettings {
+	fn default() -> Self {
+		Self {
+			count: 50,
+			ratio: 1e-3,
+			rule: LambdaRule::default(),
+		}
+	}
+}
+
+impl PathSettings {
+	/// Checks that `count` is at least 1 and `ratio` in `(0, 1)`
+	pub fn validate(&self) -> Result<(), String> {
+		if self.count == 0 {
+			return Err(String::from("Invalid path length 0, expected at least 1"));
+		}
+		if !(self.ratio > 0.0 && self.ratio < 1.0) {
+			return Err(format!(
+				"Invalid path ratio {}, expected 0 to 1 (exclusive)",
+				self.ratio
+			));
+		}
+		Ok(())
+	}
+
+	/// Descending log-spaced lambdas for the scaled samples
+	pub fn lambdas(&self, scaled: &[Sample], regularization: &Regularization) -> Vec<f64> {
+		let max = lambda_max(scaled, regularization);
+		match self.count {
+			0 => Vec::new(),
+			1 => vec![max],
+			n => (0..n)
+				.map(|i| max * self.ratio.powf(i as f64 / (n - 1) as f64))
+				.collect(),
+		}
+	}
+}
+
+/// Smallest lambda for which the lasso keeps all coefficients at zero
+///
+/// With the intercept fitting the mean, that is the largest weighted covariance of a scaled
+/// feature with the target. Ridge and mostly-ridge elastic nets use the L1 share of
+/// `0.001` instead, as glmnet does.
+pub fn lambda_max(scaled: &[Sample], regularization: &Regularization) -> f64 {
+	let m = match total_weight(scaled) {
+		w if w > 0.0 => w,
+		_ => 1.0,
+	};
+	let mean_y = scaled.iter().map(|s| s.weight * s.target).sum::<f64>() / m;
+	let feature_count = scaled.first().map_or(0, |s| s.features.len());
+	let covariance = (0..feature_count)
+		.map(|j| {
+			(scaled
+				.iter()
+				.map(|s| s.weight * s.features[j] * (s.target - mean_y))
+				.sum::<f64>()
+				/ m)
+				.abs()
+		})
+		.fold(0.0, f64::max);
+	match covariance > 0.0 {
+		// Nudged up so rounding doesn't let the first feature in
+		true => covariance / regularization.mixing().max(MIN_MIXING) * (1.0 + 1e-9),
+		// Constant target or features, any lambda will do
+		false => 1.0,
+	}
+}
+
+/// Coordinate descent fits along the lambdas, each starting from the previous one
+///
+/// Samples are scaled as for the gradient descent, the returned theta apply to raw values.
+pub fn fit_path(
+	samples: &[Sample],
+	scaling: &SampleScaling,
+	regularization: &Regularization,
+	lambdas: &[f64],
+	tolerance: f64,
+	max_sweeps: usize,
+) -> Vec<Vec<f64>> {
+	let scaled: Vec<Sample> = samples.iter().map(|s| scaling.apply(s)).collect();
+	let mut theta = vec![0.0; scaling.features.len() + 1];
+	lambdas
+		.iter()
+		.map(|lambda| {
+			let penalized = Regularization {
+				lambda: *lambda,
+				..*regularization
+			};
+			theta = coordinate_descent(&scaled, &penalized, &theta, tolerance, max_sweeps).0;
+			scaling.theta_to_raw(&theta)
+		})
+		.collect()
+}
+
+/// Fitted path with the cross-validated error of every lambda
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegularizationPath {
+	/// Descending lambdas
+	pub lambdas: Vec<f64>,
+	/// Theta for raw values fitted on the whole dataset, per lambda
+	pub coefficients: Vec<Vec<f64>>,
+	/// Mean of the test set mean squared errors across folds, per lambda
+	pub cv_mse: Vec<f64>,
+	/// Standard error of `cv_mse`
+	pub cv_se: Vec<f64>,
+	pub lambda_min: f64,
+	pub lambda_1se: f64,
+	pub rule: LambdaRule,
+}
+
+impl RegularizationPath {
+	/// Picks `lambda_min` and `lambda_1se` from the errors
+	///
+	/// `fold_mse` holds the test set errors of every fold, per lambda.
+	pub fn select(
+		lambdas: Vec<f64>,
+		coefficients: Vec<Vec<f64>>,
+		fold_mse: &[Vec<f64>],
+		rule: LambdaRule,
+	) -> Self {
+		let (cv_mse, cv_se): (Vec<f64>, Vec<f64>) = fold_mse
+			.iter()
+			.map(|errors| {
+				let k = errors.len() as f64;
+				let mean = errors.iter().sum::<f64>() / k;
+				let var = errors.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / (k - 1.0);
+				match errors.len() > 1 {
+					true => (mean, (var / k).sqrt()),
+					false => (mean, 0.0),
+				}
+			})
+			.unzip();
+		let best = (0..cv_mse.len())
+			.min_by(|a, b| cv_mse[*a].total_cmp(&cv_mse[*b]))
+			.unwrap_or(0);
+		let threshold = cv_mse.get(best).map_or(f64::NAN, |mse| mse + cv_se[best]);
+		// Lambdas are descending, so the first one under the threshold is the largest
+		let one_se = (0..=best).find(|i| cv_mse[*i] <= threshold).unwrap_or(best);
+		Self {
+			lambda_min: lambdas.get(best).cloned().unwrap_or(f64::NAN),
+			lambda_1se: lambdas.get(one_se).cloned().unwrap_or(f64::NAN),
+			lambdas,
+			coefficients,
+			cv_mse,
+			cv_se,
+			rule,
+		}
+	}
+
+	/// Lambda picked by the rule
+	pub fn chosen(&self) -> f64 {
+		match self.rule {
+			LambdaRule::Min => self.lambda_min,
+			LambdaRule::OneSe => self.lambda_1se,
+		}
+	}
+}
+
+/// Table of lambda, number of non-zero coefficients and the cross-validated error
+impl fmt::Display for RegularizationPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Regularization path over {} lambdas\n{:>14}{:>10}{:>16}{:>16}",
+			self.lambdas.len(),
+			"lambda",
+			"nonzero",
+			"cv_mse",
+			"cv_se"
+		)?;
+		for (i, lambda) in self.lambdas.iter().enumerate() {
+			let mark = match *lambda {
+				l if l == self.lambda_min && l == self.lambda_1se => "  min, 1se",
+				l if l == self.lambda_min => "  min",
+				l if l == self.lambda_1se => "  1se",
+				_ => "",
+			};
+			write!(
+				f,
+				"\n{:>14.4e}{:>10}{:>16.4}{:>16.4}{}",
+				lambda,
+				self.coefficients[i][1..]
+					.iter()
+					.filter(|t| **t != 0.0)
+					.count(),
+				self.cv_mse[i],
+				self.cv_se[i],
+				mark
+			)?;
+		}
+		write!(f, "\nChosen lambda {:e} ({})", self.chosen(), self.rule)
+	}
+}
+
+/// Weighted mean squared error of theta on the samples
+pub fn mse(theta: &[f64], samples: &[Sample]) -> f64 {
+	samples
+		.iter()
+		.map(|s| s.weight * (s.target - dot(theta, &s.features)).powi(2))
+		.sum::<f64>()
+		/ total_weight(samples)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::regularization::Penalty;
+	use crate::scaler::Scaler;
+
+	#[test]
+	fn lasso_path() {
+		// y = 1 + 2a - b with a small third feature of noise
+		let samples: Vec<Sample> = (0..30)
+			.map(|i| {
+				let (a, b, c) = ((i % 5) as f64, (i / 5) as f64, ((i * 7) % 3) as f64 * 0.01);
+				Sample::new(vec![a, b, c], 1.0 + 2.0 * a - b)
+			})
+			.collect();
+		let lasso = Regularization {
+			penalty: Penalty::Lasso,
+			lambda: 1.0,
+			l1_ratio: 0.5,
+		};
+		let scaling = SampleScaling::fit(&samples, Scaler::ZScore, Scaler::None);
+		let scaled: Vec<Sample> = samples.iter().map(|s| scaling.apply(s)).collect();
+		let settings = PathSettings::default();
+		let lambdas = settings.lambdas(&scaled, &lasso);
+		assert_eq!(lambdas.len(), 50);
+		assert!((lambdas[49] / lambdas[0] - 1e-3).abs() < 1e-12);
+		let path = fit_path(&samples, &scaling, &lasso, &lambdas, 1e-12, 10_000);
+		// Nothing enters at lambda_max, all features are in at the end
+		assert!(path[0][1..].iter().all(|t| *t == 0.0), "{:?}", path[0]);
+		assert!((path[0][0] - samples.iter().map(|s| s.target).sum::<f64>() / 30.0).abs() < 1e-9);
+		assert!(path[1][1] != 0.0);
+		assert!((path[49][1] - 2.0).abs() < 0.01 && (path[49][2] + 1.0).abs() < 0.01);
+		assert!(mse(&path[49], &samples) < mse(&path[10], &samples));
+		// Ridge has a finite lambda_max as well
+		let ridge = Regularization {
+			penalty: Penalty::Ridge,
+			..lasso
+		};
+		assert!((lambda_max(&scaled, &ridge) / lambda_max(&scaled, &lasso) - 1e3).abs() < 1e-6);
+	}
+
+	#[test]
+	fn invalid_settings() {
+		let settings = PathSettings::default();
+		assert!(settings.validate().is_ok());
+		assert!(PathSettings {
+			count: 0,
+			..settings
+		}
+		.validate()
+		.is_err());
+		for ratio in [0.0, 1.0, 1.5, -0.1, f64::NAN] {
+			assert!(
+				PathSettings { ratio, ..settings }.validate().is_err(),
+				"{}",
+				ratio
+			);
+		}
+	}
+
+	#[test]
+	fn selection() {
+		let lambdas = vec![8.0, 4.0, 2.0, 1.0];
+		let coefficients = vec![vec![0.0, 0.0]; 4];
+		let fold_mse = vec![
+			vec![10.0, 12.0],
+			vec![5.0, 7.0],
+			vec![4.0, 6.0],
+			vec![4.5, 5.5],
+		];
+		let path = RegularizationPath::select(lambdas, coefficients, &fold_mse, LambdaRule::OneSe);
+		assert_eq!(path.cv_mse, vec![11.0, 6.0, 5.0, 5.0]);
+		assert_eq!(path.cv_se[0], 1.0);
+		// Ties are resolved to the larger lambda
+		assert_eq!(path.lambda_min, 2.0);
+		// 6 is within 5 + 1
+		assert_eq!(path.lambda_1se, 4.0);
+		assert_eq!(path.chosen(), 4.0);
+		assert_eq!(
+			RegularizationPath {
+				rule: LambdaRule::Min,
+				..path.clone()
+			}
+			.chosen(),
+			2.0
+		);
+		assert_eq!(path.to_string().lines().count(), 7);
+		assert_eq!("1se".parse(), Ok(LambdaRule::OneSe));
+	}
+}
@@ -0,0 +1,200 @@
+//! Helper module that handles command line arguments
+use clap::{crate_name, crate_version, values_t};
+use clap::{App, Arg, ArgMatches};
+
+use ft_linear_regression::csv::{self, CsvOptions};
+use ft_linear_regression::dataset::Column;
+use ft_linear_regression::domain::Extrapolation;
+use ft_linear_regression::inference::IntervalKind;
+use ft_linear_regression::predictor::{BadRows, BatchOptions, OutputFormat};
+
+pub struct CmdArgs<'a> {
+	pub matches: ArgMatches<'a>,
+}
+
+impl<'a> CmdArgs<'a> {
+	/// Default constructor
+	pub fn new() -> Self {
+		let matches = App::new(crate_name!())
+		.author(env!("CARGO_PKG_AUTHORS"))
+		.version(crate_version!())
+		.arg(
+			Arg::with_name("key")
+				.short("k")
+				.long("key")
+				.takes_value(true)
+				.multiple(true)
+				.use_delimiter(true)
+				.allow_hyphen_values(true)
+				.conflicts_with("input")
+				.help("Key to use in value estimation, using trained linear regression model. Comma separated feature vector for multivariate models."),
+		)
+		.arg(
+			Arg::with_name("model")
+				.short("f")
+				.long("modelfile")
+				.takes_value(true)
+				.help("Path to trained linear regression model")
+				.required(true),
+		)
+		.arg(
+			Arg::with_name("input")
+				.short("i")
+				.long("input")
+				.takes_value(true)
+				.help("CSV file with keys to predict in batch, - reads from stdin"),
+		)
+		.arg(
+			Arg::with_name("output")
+				.short("o")
+				.long("output")
+				.takes_value(true)
+				.requires("input")
+				.help("Path to write the batch predictions to [default: stdout]"),
+		)
+		.arg(
+			Arg::with_name("features")
+				.long("features")
+				.takes_value(true)
+				.multiple(true)
+				.use_delimiter(true)
+				.requires("input")
+				.help("Comma separated input columns holding the keys, header names or zero-based indices [default: model feature names]"),
+		)
+		.arg(
+			Arg::with_name("delimiter")
+				.short("d")
+				.long("delimiter")
+				.takes_value(true)
+				.validator(|d| csv::parse_delimiter(&d).map(|_| ()))
+				.help("Field delimiter of the input, single character or \"tab\" [default: ,]"),
+		)
+		.arg(
+			Arg::with_name("noheader")
+				.long("no-header")
+				.help("Input has no header line, keys are taken from the first columns"),
+		)
+		.arg(
+			Arg::with_name("format")
+				.long("format")
+				.takes_value(true)
+				.conflicts_with("input")
+				.possible_values(&["text", "json", "csv", "raw"])
+				.help("Output of a single prediction, sentence, JSON object, CSV with header or the bare number [default: text]"),
+		)
+		.arg(
+			Arg::with_name("interval")
+				.long("interval")
+				.takes_value(true)
+				.conflicts_with("input")
+				.possible_values(&["confidence", "prediction"])
+				.help("Reports the range of the mean response or of a new observation around the estimate"),
+		)
+		.arg(
+			Arg::with_name("level")
+				.long("level")
+				.takes_value(true)
+				.requires("interval")
+				.help("Probability that the interval contains the value [default: 0.95]"),
+		)
+		.arg(
+			Arg::with_name("extrapolation")
+				.long("extrapolation")
+				.takes_value(true)
+				.possible_values(&["warn", "refuse", "clamp"])
+				.help("Handling of keys outside the training range, predict with a warning, refuse or clamp them into the range [default: warn]"),
+		)
+		.arg(
+			Arg::with_name("badrows")
+				.long("bad-rows")
+				.takes_value(true)
+				.possible_values(&["fail", "skip", "blank"])
+				.help("Handling of input rows with missing or invalid keys, fail, leave them out or keep them with empty prediction [default: fail]"),
+		)
+		.get_matches();
+		Self { matches }
+	}
+
+	/// Model file getter
+	pub fn get_modelfile(&self) -> &str {
+		self.matches.value_of("model").unwrap()
+	}
+
+	/// Keys getter, `Option::None` if not given or not numbers
+	pub fn get_keys(&self) -> Option<Vec<f64>> {
+		let matches = &self.matches;
+		values_t!(matches, "key", f64).ok()
+	}
+
+	/// Output format getter
+	pub fn get_format(&self) -> OutputFormat {
+		self.matches
+			.value_of("format")
+			.unwrap_or("text")
+			.parse::<OutputFormat>()
+			.expect("Couldn't parse output format")
+	}
+
+	/// Interval kind and level getter, `Option::None` if not requested
+	pub fn get_interval(&self) -> Option<(IntervalKind, f64)> {
+		let kind = self
+			.matches
+			.value_of("interval")?
+			.parse::<IntervalKind>()
+			.expect("Couldn't parse interval");
+		let level = self
+			.matches
+			.value_of("level")
+			.unwrap_or("0.95")
+			.parse::<f64>()
+			.expect("Couldn't parse level");
+		Some((kind, level))
+	}
+
+	/// Extrapolation policy getter
+	pub fn get_extrapolation(&self) -> Extrapolation {
+		self.matches
+			.value_of("extrapolation")
+			.unwrap_or("warn")
+			.parse::<Extrapolation>()
+			.expect("Couldn't parse extrapolation policy")
+	}
+
+	/// Batch input getter, `-` stands for stdin
+	pub fn get_input(&self) -> Option<&str> {
+		self.matches.value_of("input")
+	}
+
+	/// Batch output getter, `Option::None` for stdout
+	pub fn get_output(&self) -> Option<&str> {
+		self.matches.value_of("output")
+	}
+
+	/// Field delimiter getter, accepts `tab` and `\t` for tab
+	pub fn get_delimiter(&self) -> char {
+		csv::parse_delimiter(self.matches.value_of("delimiter").unwrap_or(","))
+			.expect("Couldn't parse delimiter")
+	}
+}
+
+impl<'a> From<&CmdArgs<'a>> for BatchOptions {
+	fn from(cmdargs: &CmdArgs<'a>) -> Self {
+		Self {
+			csv: CsvOptions {
+				delimiter: cmdargs.get_delimiter(),
+				has_header: !cmdargs.matches.is_present("noheader"),
+			},
+			features: cmdargs
+				.matches
+				.values_of("features")
+				.map(|values| values.map(Column::from).collect()),
+			bad_rows: cmdargs
+				.matches
+				.value_of("badrows")
+				.unwrap_or("fail")
+				.parse::<BadRows>()
+				.expect("Couldn't parse bad row policy"),
+			extrapolation: cmdargs.get_extrapolation(),
+		}
+	}
+}
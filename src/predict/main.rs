@@ -1,35 +1,64 @@
 //! # Linear Regression Predictor
-//! Uses a model trained by `train` crate to estimate values from a given key.
+//! Uses a model trained by `train` crate to estimate values from a given key,
+//! or from every row of a CSV file in batch mode.
 //! ## Usage
 //! ```text
 //! USAGE:
-//! predict [OPTIONS] --modelfile <model>
+//! predict [FLAGS] [OPTIONS] --modelfile <model>
 //!
 //! FLAGS:
 //!     -h, --help       Prints help information
+//!         --no-header  Input has no header line, keys are taken from the first columns
 //!     -V, --version    Prints version information
 //!
 //! OPTIONS:
-//!     -k, --key <key>            Key to use in value estimation, using trained linear regression model.
+//!         --bad-rows <badrows>   Handling of input rows with missing or invalid keys, fail, leave them out or keep
+//!                                them with empty prediction [default: fail]  [possible values: fail, skip, blank]
+//!     -d, --delimiter <delimiter>
+//!                                Field delimiter of the input, single character or "tab" [default: ,]
+//!         --extrapolation <extrapolation>
+//!                                Handling of keys outside the training range, predict with a warning, refuse or
+//!                                clamp them into the range [default: warn]  [possible values: warn, refuse, clamp]
+//!         --features <features>...
+//!                                Comma separated input columns holding the keys, header names or zero-based indices
+//!                                [default: model feature names]
+//!         --format <format>      Output of a single prediction, sentence, JSON object, CSV with header or the bare
+//!                                number [default: text]  [possible values: text, json, csv, raw]
+//!         --interval <interval>  Reports the range of the mean response or of a new observation around the estimate
+//!                                [possible values: confidence, prediction]
+//!     -i, --input <input>        CSV file with keys to predict in batch, - reads from stdin
+//!     -k, --key <key>...         Key to use in value estimation, using trained linear regression model.
+//!                                Comma separated feature vector for multivariate models.
+//!         --level <level>        Probability that the interval contains the value [default: 0.95]
 //!     -f, --modelfile <model>    Path to trained linear regression model
+//!     -o, --output <output>      Path to write the batch predictions to [default: stdout]
 //! ```
-use clap::{crate_authors, crate_name, crate_version, value_t};
-use clap::{App, Arg};
+use std::fs::{self, File};
+use std::io::{stdin, stdout, BufWriter, Read, Write};
+
+use ft_linear_regression::domain::Extrapolation;
+use ft_linear_regression::predictor::{BatchOptions, OutputFormat};
+use ft_linear_regression::{Predictor, Result, TrainError};
+mod arguments;
+use arguments::CmdArgs;
 
-mod predictor;
-use predictor::Predictor;
-use std::io::Result;
 /// Handles user input
 ///
+/// Asks for each feature of the model in turn.
+fn ask_keys(predictor: &Predictor) -> Vec<f64> {
+	predictor
+		.get_labels()
+		.iter()
+		.map(|label| ask_key(label, predictor.get_target_label()))
+		.collect()
+}
+
 /// Helper function that handles user input.
-fn ask_key(labels: &[String; 2]) -> f64 {
+fn ask_key(label: &str, target: &str) -> f64 {
 	use std::io::{stdin, stdout, Write};
 	let mut s = String::new();
 	let mut val: Option<f64> = Option::None;
-	println!(
-		"Please insert a key [{}] to estimate [{}]",
-		labels[0], labels[1]
-	);
+	println!("Please insert a key [{}] to estimate [{}]", label, target);
 	while val.is_none() {
 		let _ = stdout().flush();
 		stdin()
@@ -45,34 +74,78 @@ fn ask_key(labels: &[String; 2]) -> f64 {
 	}
 	val.unwrap()
 }
+/// Predicts all rows of the input CSV, reporting to stderr to keep stdout for the data
+fn predict_batch(predictor: &Predictor, cmdargs: &CmdArgs, input: &str) -> Result<()> {
+	let contents = match input {
+		"-" => {
+			let mut contents = String::new();
+			stdin().read_to_string(&mut contents)?;
+			contents
+		}
+		path => fs::read_to_string(path)?,
+	};
+	let options = BatchOptions::from(cmdargs);
+	let report = match cmdargs.get_output() {
+		Some(path) => {
+			let mut out = BufWriter::new(File::create(path)?);
+			let report = predictor.predict_csv(&contents, &options, &mut out)?;
+			out.flush()?;
+			report
+		}
+		None => predictor.predict_csv(&contents, &options, &mut stdout().lock())?,
+	};
+	for warning in report.warnings.iter() {
+		eprintln!("{}", warning);
+	}
+	eprintln!("{}", report);
+	Ok(())
+}
+
 /// Main
 fn main() -> Result<()> {
-	println!("\n\t## PREDICTOR ##\n");
-	let matches = App::new(crate_name!())
-		.author(crate_authors!("\n"))
-		.version(crate_version!())
-		.arg(
-			Arg::with_name("key")
-				.short("k")
-				.long("key")
-				.takes_value(true)
-				.allow_hyphen_values(true)
-				.help("Key to use in value estimation, using trained linear regression model."),
-		)
-		.arg(
-			Arg::with_name("model")
-				.short("f")
-				.long("modelfile")
-				.takes_value(true)
-				.help("Path to trained linear regression model")
-				.required(true),
-		)
-		.get_matches();
-	let modelfile = matches.value_of("model").unwrap();
-	let predictor = Predictor::load(modelfile)?;
+	let cmdargs = CmdArgs::new();
+	let predictor = Predictor::load(cmdargs.get_modelfile())?;
+	if let Some(input) = cmdargs.get_input() {
+		return predict_batch(&predictor, &cmdargs, input);
+	}
+	let format = cmdargs.get_format();
+	if format == OutputFormat::Text {
+		println!("\n\t## PREDICTOR ##\n");
+	}
 
-	let val: f64 =
-		value_t!(matches, "key", f64).unwrap_or_else(|_| ask_key(predictor.get_labels()));
-	predictor.predict(val);
+	let features: Vec<f64> = cmdargs.get_keys().unwrap_or_else(|| ask_keys(&predictor));
+	if features.len() != predictor.get_labels().len() {
+		return Err(TrainError::Custom(format!(
+			"Expected {} keys ({}), got {}",
+			predictor.get_labels().len(),
+			predictor.get_labels().join(", "),
+			features.len()
+		)));
+	}
+	let policy = cmdargs.get_extrapolation();
+	let keys = predictor.constrain(&features, policy)?;
+	if policy == Extrapolation::Warn {
+		for warning in predictor.warnings(&predictor.predict(&keys)) {
+			eprintln!("{}", warning);
+		}
+	} else if keys != features {
+		eprintln!("Keys clamped into the training range: {:?}", keys);
+	}
+	let prediction = match cmdargs.get_interval() {
+		None => predictor.predict(&keys),
+		Some((_, level)) if !(level > 0.0 && level < 1.0) => {
+			return Err(TrainError::Custom(format!(
+				"Interval level has to be between 0 and 1, got {}",
+				level
+			)));
+		}
+		Some(_) if predictor.metadata().inference.is_none() => {
+			return Err(TrainError::Custom(String::from(
+				"Model has no statistics for intervals, retrain it and save as JSON",
+			)));
+		}
+		Some((kind, level)) => predictor.predict_interval(&keys, kind, level),
+	};
+	println!("{}", predictor.render(&prediction, format));
 	Ok(())
 }
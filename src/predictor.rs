@@ -0,0 +1,465 @@
+//! Module responsible for predictions
+//!
+//! Handles model loading and predictions based on input, single feature vectors
+//! or whole CSV tables.
+use std::fmt;
+use std::io::{Result, Write};
+use std::str::FromStr;
+
+use crate::csv::{self, CsvOptions};
+use crate::dataset::Column;
+use crate::domain::Extrapolation;
+use crate::inference::IntervalKind;
+use crate::json::Value;
+use crate::missing::{parse_field, to_error};
+use crate::model::LinearModel;
+use crate::model_io::{self, Metadata, SavedModel};
+use crate::result::TrainError;
+
+/// Range expected to contain the value with given probability
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+	pub kind: IntervalKind,
+	/// Probability, e.g. `0.95`
+	pub level: f64,
+	pub lower: f64,
+	pub upper: f64,
+}
+
+/// Result of a single prediction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+	/// Keys the estimate is made for
+	pub features: Vec<f64>,
+	pub estimate: f64,
+	pub interval: Option<Interval>,
+	/// Indices of the keys outside the training domain
+	pub extrapolated: Vec<usize>,
+}
+
+/// Rendering of a [`Prediction`], see [`Predictor::render`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+	/// Sentence for humans
+	#[default]
+	Text,
+	/// Object with features, target, estimate and interval bounds if any
+	Json,
+	/// Header and a single row of features, estimate and interval bounds if any
+	Csv,
+	/// Estimate followed by interval bounds if any, separated by spaces
+	Raw,
+}
+
+/// Accepts `text`, `json`, `csv` and `raw`
+impl FromStr for OutputFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"text" => Ok(OutputFormat::Text),
+			"json" => Ok(OutputFormat::Json),
+			"csv" => Ok(OutputFormat::Csv),
+			"raw" => Ok(OutputFormat::Raw),
+			_ => Err(format!("Unknown output format {:?}", s)),
+		}
+	}
+}
+
+/// Handling of input rows that can't be predicted, because of missing or invalid keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BadRows {
+	/// Stop with an error pointing to the invalid cell
+	#[default]
+	Fail,
+	/// Leave the row out of the output
+	Skip,
+	/// Keep the row with empty prediction
+	Blank,
+}
+
+/// Accepts `fail`, `skip` and `blank`
+impl FromStr for BadRows {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"fail" => Ok(BadRows::Fail),
+			"skip" => Ok(BadRows::Skip),
+			"blank" => Ok(BadRows::Blank),
+			_ => Err(format!("Unknown bad row policy {:?}", s)),
+		}
+	}
+}
+
+/// Batch prediction configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchOptions {
+	/// Format of the input, also used for the output
+	pub csv: CsvOptions,
+	/// Columns holding the keys, in the order of model features
+	///
+	/// By default the columns are looked up by the model feature labels,
+	/// or the first columns are used if the input has no header.
+	pub features: Option<Vec<Column>>,
+	pub bad_rows: BadRows,
+	/// Handling of keys outside the training domain, refused rows are bad rows
+	pub extrapolation: Extrapolation,
+}
+
+/// Summary of a batch prediction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+	/// Rows of the input
+	pub rows: usize,
+	/// Rows with a prediction
+	pub predicted: usize,
+	/// Bad rows left out of the output
+	pub skipped: usize,
+	/// Bad rows written with empty prediction
+	pub blank: usize,
+	/// Rows with keys outside the training domain, before clamping
+	pub extrapolated: usize,
+	/// Warnings about the extrapolated keys for [`Extrapolation::Warn`], with their line
+	pub warnings: Vec<String>,
+}
+
+impl fmt::Display for BatchReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Predicted {} of {} rows ({} skipped, {} left blank, {} outside the training range)",
+			self.predicted, self.rows, self.skipped, self.blank, self.extrapolated
+		)
+	}
+}
+
+#[derive(Debug)]
+pub struct Predictor {
+	model: LinearModel,
+	metadata: Metadata,
+}
+
+impl Predictor {
+	/// Loads model from a file of any supported format
+	///
+	/// See [`model_io`] for the formats, malformed files are [`std::io::ErrorKind::InvalidData`].
+	///
+	pub fn load(filename: &str) -> Result<Self> {
+		Ok(Self::from(model_io::load_saved(filename)?))
+	}
+
+	/// Feature labels getter
+	pub fn get_labels(&self) -> &[String] {
+		&self.model.feature_labels
+	}
+
+	/// Target label getter
+	pub fn get_target_label(&self) -> &str {
+		&self.model.target_label
+	}
+
+	/// Model getter
+	pub fn model(&self) -> &LinearModel {
+		&self.model
+	}
+
+	/// Metadata of the loaded model, empty for legacy files
+	pub fn metadata(&self) -> &Metadata {
+		&self.metadata
+	}
+
+	/// Makes the prediction for given feature vector
+	///
+	/// Uses formula:
+	/// ```text
+	/// estimate = theta[0] + (theta[1] * features[0]) + ... + (theta[n] * features[n - 1])
+	/// ```
+	/// Panics if the length of `features` doesn't match the model.
+	pub fn predict(&self, features: &[f64]) -> Prediction {
+		Prediction {
+			features: features.to_vec(),
+			estimate: self.model.estimate(features),
+			interval: Option::None,
+			extrapolated: self
+				.metadata
+				.domain
+				.as_ref()
+				.map_or(vec![], |domain| domain.outside(features)),
+		}
+	}
+
+	/// Applies the extrapolation policy to the keys, models without domain accept any keys
+	///
+	/// Returns the keys to predict for, clamped for [`Extrapolation::Clamp`], or
+	/// [`TrainError::OutOfDomain`] for the first key outside the domain for [`Extrapolation::Refuse`].
+	pub fn constrain(&self, features: &[f64], policy: Extrapolation) -> crate::Result<Vec<f64>> {
+		let domain = match self.metadata.domain {
+			Some(ref domain) => domain,
+			None => return Ok(features.to_vec()),
+		};
+		match (policy, domain.outside(features).first()) {
+			(Extrapolation::Refuse, Some(&j)) => Err(TrainError::OutOfDomain {
+				line: Option::None,
+				feature: self.model.feature_labels[j].clone(),
+				value: features[j],
+				min: domain.features[j].min,
+				max: domain.features[j].max,
+			}),
+			(Extrapolation::Clamp, _) => Ok(domain.clamp(features)),
+			_ => Ok(features.to_vec()),
+		}
+	}
+
+	/// Warnings about the keys of the prediction outside the training domain
+	pub fn warnings(&self, prediction: &Prediction) -> Vec<String> {
+		self.extrapolations(prediction)
+			.map(|message| format!("Warning: {}", message))
+			.collect()
+	}
+
+	/// Descriptions of the keys outside the training domain
+	fn extrapolations<'a>(
+		&'a self,
+		prediction: &'a Prediction,
+	) -> impl Iterator<Item = String> + 'a {
+		let domain = self.metadata.domain.as_ref();
+		prediction.extrapolated.iter().filter_map(move |&j| {
+			domain.map(|domain| {
+				format!(
+					"key {} [{}] is outside the training range {} to {}, the estimate is extrapolated",
+					prediction.features[j],
+					self.model.feature_labels[j],
+					domain.features[j].min,
+					domain.features[j].max
+				)
+			})
+		})
+	}
+
+	/// Makes the prediction with two-sided interval at `level`, e.g. `0.95`
+	///
+	/// The interval is `Option::None` if the model was saved without [`Inference`](crate::inference::Inference).
+	pub fn predict_interval(&self, features: &[f64], kind: IntervalKind, level: f64) -> Prediction {
+		let mut prediction = self.predict(features);
+		prediction.interval = self.metadata.inference.as_ref().map(|inference| {
+			let (lower, upper) = inference.interval(prediction.estimate, features, kind, level);
+			Interval {
+				kind,
+				level,
+				lower,
+				upper,
+			}
+		});
+		prediction
+	}
+
+	/// Renders the prediction in given format, without trailing newline
+	pub fn render(&self, prediction: &Prediction, format: OutputFormat) -> String {
+		let labels = &self.model.feature_labels;
+		let target = &self.model.target_label;
+		match format {
+			OutputFormat::Text => {
+				let keys: Vec<String> = prediction
+					.features
+					.iter()
+					.zip(labels.iter())
+					.map(|(x, label)| format!("{} [{}]", x, label))
+					.collect();
+				let interval = prediction.interval.map_or(String::new(), |i| {
+					format!(
+						", {}% {} interval {:.3} to {:.3}",
+						i.level * 100.0,
+						i.kind,
+						i.lower,
+						i.upper
+					)
+				});
+				format!(
+					"The estimate for {} is {:.3} [{}]{}.",
+					keys.join(", "),
+					prediction.estimate,
+					target,
+					interval
+				)
+			}
+			OutputFormat::Json => {
+				let mut members = vec![
+					(
+						String::from("features"),
+						Value::Object(
+							labels
+								.iter()
+								.zip(prediction.features.iter())
+								.map(|(l, x)| (l.clone(), Value::number(*x)))
+								.collect(),
+						),
+					),
+					(String::from("target"), Value::String(target.clone())),
+					(String::from("estimate"), Value::number(prediction.estimate)),
+				];
+				if !prediction.extrapolated.is_empty() {
+					members.push((
+						String::from("extrapolated"),
+						Value::Array(
+							prediction
+								.extrapolated
+								.iter()
+								.map(|&j| Value::String(labels[j].clone()))
+								.collect(),
+						),
+					));
+				}
+				if let Some(i) = prediction.interval {
+					members.extend([
+						(String::from("interval"), Value::String(i.kind.to_string())),
+						(String::from("level"), Value::number(i.level)),
+						(String::from("lower"), Value::number(i.lower)),
+						(String::from("upper"), Value::number(i.upper)),
+					]);
+				}
+				Value::Object(members).to_string()
+			}
+			OutputFormat::Csv => {
+				let mut header: Vec<String> = labels.clone();
+				header.push(format!("predicted_{}", target));
+				let mut row: Vec<String> =
+					prediction.features.iter().map(|x| x.to_string()).collect();
+				row.push(prediction.estimate.to_string());
+				if let Some(i) = prediction.interval {
+					header.extend([String::from("lower"), String::from("upper")]);
+					row.extend([i.lower.to_string(), i.upper.to_string()]);
+				}
+				let mut out = Vec::new();
+				csv::write_record(&mut out, &header, ',').unwrap();
+				csv::write_record(&mut out, &row, ',').unwrap();
+				String::from_utf8(out).unwrap().trim_end().to_string()
+			}
+			OutputFormat::Raw => match prediction.interval {
+				Some(i) => format!("{} {} {}", prediction.estimate, i.lower, i.upper),
+				None => prediction.estimate.to_string(),
+			},
+		}
+	}
+
+	/// Predicts every row of CSV `contents` and writes them with an added prediction column into `out`.
+	///
+	/// The output keeps all original columns, the prediction is named `predicted_<target>`.
+	pub fn predict_csv<W: Write>(
+		&self,
+		contents: &str,
+		options: &BatchOptions,
+		out: &mut W,
+	) -> crate::Result<BatchReport> {
+		let table = csv::parse(contents, &options.csv)?;
+		let header: Vec<&str> = table.header.iter().map(|h| h.as_str()).collect();
+		let features = match options.features {
+			Some(ref features) => features.clone(),
+			None if options.csv.has_header => self
+				.model
+				.feature_labels
+				.iter()
+				.map(|l| Column::Name(l.clone()))
+				.collect(),
+			None => (0..self.model.feature_count()).map(Column::Index).collect(),
+		};
+		if features.len() != self.model.feature_count() {
+			return Err(TrainError::Custom(format!(
+				"Model expects {} feature columns ({}), got {}",
+				self.model.feature_count(),
+				self.model.feature_labels.join(", "),
+				features.len()
+			)));
+		}
+		let indices = features
+			.iter()
+			.map(|c| c.resolve(&header))
+			.collect::<crate::Result<Vec<usize>>>()?;
+
+		let delimiter = options.csv.delimiter;
+		if options.csv.has_header {
+			let mut labels = header.clone();
+			let prediction = format!("predicted_{}", self.model.target_label);
+			labels.push(&prediction);
+			csv::write_record(out, &labels, delimiter)?;
+		}
+		let mut report = BatchReport {
+			rows: table.records.len(),
+			..BatchReport::default()
+		};
+		for record in table.records.iter() {
+			let keys = indices
+				.iter()
+				.map(|i| parse_field(&record.fields[*i]))
+				.collect::<std::result::Result<Vec<f64>, _>>();
+			let keys = keys.map_err(to_error).and_then(|keys| {
+				let prediction = self.predict(&keys);
+				if prediction.extrapolated.is_empty() {
+					return Ok(keys);
+				}
+				report.extrapolated += 1;
+				if options.extrapolation == Extrapolation::Warn {
+					report
+						.warnings
+						.extend(self.extrapolations(&prediction).map(|message| {
+							format!("Warning: line {}, {}", record.line(), message)
+						}));
+				}
+				self.constrain(&keys, options.extrapolation)
+					.map_err(|err| match err {
+						TrainError::OutOfDomain {
+							feature,
+							value,
+							min,
+							max,
+							..
+						} => TrainError::OutOfDomain {
+							line: Some(record.line()),
+							feature,
+							value,
+							min,
+							max,
+						},
+						err => err,
+					})
+			});
+			let prediction = match (keys, options.bad_rows) {
+				(Ok(keys), _) => {
+					report.predicted += 1;
+					self.predict(&keys).estimate.to_string()
+				}
+				(Err(err), BadRows::Fail) => return Err(err),
+				(Err(_), BadRows::Skip) => {
+					report.skipped += 1;
+					continue;
+				}
+				(Err(_), BadRows::Blank) => {
+					report.blank += 1;
+					String::new()
+				}
+			};
+			let mut fields: Vec<&str> = record.fields.iter().map(|f| f.value.as_str()).collect();
+			fields.push(&prediction);
+			csv::write_record(out, &fields, delimiter)?;
+		}
+		Ok(report)
+	}
+}
+
+impl From<LinearModel> for Predictor {
+	fn from(model: LinearModel) -> Self {
+		Self {
+			model,
+			metadata: Metadata::default(),
+		}
+	}
+}
+
+impl From<SavedModel> for Predictor {
+	fn from(saved: SavedModel) -> Self {
+		Self {
+			model: saved.model,
+			metadata: saved.metadata,
+		}
+	}
+}
@@ -0,0 +1,217 @@
+//! Ridge, lasso and elastic net regularization
+//!
+//! The penalized cost of theta fitted on the scaled features is:
+//! ```text
+//! cost = (1 / 2m) * Sum(i)(estimate(x[i]) - y[i])^2
+//!      + lambda * (l1_ratio * Sum(j)|theta[j]| + (1 - l1_ratio) / 2 * Sum(j)theta[j]^2)
+//! ```
+//! where `j` runs over the features only, the intercept `theta[0]` is never penalized.
+//! The penalty applies to the coefficients of the scaled features, so all features
+//! are shrunk alike whatever their units, and `lambda` means the same for every solver.
+use std::fmt;
+use std::str::FromStr;
+
+/// Type of the penalty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Penalty {
+	/// Ordinary least squares
+	#[default]
+	None,
+	/// L2 penalty, shrinks correlated coefficients towards each other
+	Ridge,
+	/// L1 penalty, drives some coefficients to exactly zero
+	Lasso,
+	/// Mix of L1 and L2 by `l1_ratio`
+	ElasticNet,
+}
+
+/// Accepts `none`, `ridge`, `lasso` and `elastic-net`
+impl FromStr for Penalty {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(Penalty::None),
+			"ridge" => Ok(Penalty::Ridge),
+			"lasso" => Ok(Penalty::Lasso),
+			"elastic-net" => Ok(Penalty::ElasticNet),
+			_ => Err(format!("Unknown penalty {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Penalty {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Penalty::None => write!(f, "none"),
+			Penalty::Ridge => write!(f, "ridge"),
+			Penalty::Lasso => write!(f, "lasso"),
+			Penalty::ElasticNet => write!(f, "elastic-net"),
+		}
+	}
+}
+
+/// Penalty with its strength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Regularization {
+	pub penalty: Penalty,
+	/// Strength `lambda`, at least 0
+	pub lambda: f64,
+	/// Share of the L1 penalty in `[0, 1]`, used by `Penalty::ElasticNet` only
+	pub l1_ratio: f64,
+}
+
+impl Default for Regularization {
+	fn default() -> Self {
+		Self {
+			penalty: Penalty::None,
+			lambda: 1.0,
+			l1_ratio: 0.5,
+		}
+	}
+}
+
+impl Regularization {
+	/// Whether the cost is penalized at all
+	pub fn is_none(&self) -> bool {
+		self.penalty == Penalty::None || self.lambda == 0.0
+	}
+
+	/// Effective share of the L1 penalty, 0 for ridge and 1 for lasso
+	pub fn mixing(&self) -> f64 {
+		match self.penalty {
+			Penalty::None | Penalty::Ridge => 0.0,
+			Penalty::Lasso => 1.0,
+			Penalty::ElasticNet => self.l1_ratio,
+		}
+	}
+
+	/// Strength of the L1 penalty, `lambda * l1_ratio`
+	pub fn l1(&self) -> f64 {
+		match self.penalty {
+			Penalty::None => 0.0,
+			_ => self.lambda * self.mixing(),
+		}
+	}
+
+	/// Strength of the L2 penalty, `lambda * (1 - l1_ratio)`
+	pub fn l2(&self) -> f64 {
+		match self.penalty {
+			Penalty::None => 0.0,
+			_ => self.lambda * (1.0 - self.mixing()),
+		}
+	}
+
+	/// Checks that `lambda` and `l1_ratio` are in range
+	pub fn validate(&self) -> Result<(), String> {
+		if !(self.lambda >= 0.0 && self.lambda.is_finite()) {
+			return Err(format!(
+				"Invalid lambda {}, expected at least 0",
+				self.lambda
+			));
+		}
+		if !(0.0..=1.0).contains(&self.l1_ratio) {
+			return Err(format!(
+				"Invalid l1 ratio {}, expected 0 to 1",
+				self.l1_ratio
+			));
+		}
+		Ok(())
+	}
+
+	/// Penalty term of the cost
+	pub fn cost(&self, theta: &[f64]) -> f64 {
+		let coefficients = theta.iter().skip(1);
+		let l1: f64 = coefficients.clone().map(|t| t.abs()).sum();
+		let l2: f64 = coefficients.map(|t| t * t).sum();
+		self.l1() * l1 + self.l2() / 2.0 * l2
+	}
+
+	/// Adds the gradient of the L2 penalty, the L1 part is handled by [`Regularization::shrink`]
+	pub fn add_gradient(&self, theta: &[f64], gradient: &mut [f64]) {
+		let l2 = self.l2();
+		for (g, t) in gradient.iter_mut().zip(theta.iter()).skip(1) {
+			*g += l2 * t;
+		}
+	}
+
+	/// Proximal step of the L1 penalty after a gradient step of size `step`
+	///
+	/// Soft-thresholds the coefficients, which sets the small ones to exactly zero.
+	/// Returns the temporal difference subtracted from theta, as
+	/// [`Optimizer::step`](crate::optimizer::Optimizer::step) does.
+	pub fn shrink(&self, theta: &mut [f64], step: f64) -> Vec<f64> {
+		let threshold = self.l1() * step;
+		let mut diff = vec![0.0; theta.len()];
+		if threshold > 0.0 {
+			for (t, d) in theta.iter_mut().zip(diff.iter_mut()).skip(1) {
+				let shrunk = soft_threshold(*t, threshold);
+				*d = *t - shrunk;
+				*t = shrunk;
+			}
+		}
+		diff
+	}
+}
+
+/// `sign(x) * max(|x| - threshold, 0)`
+pub fn soft_threshold(x: f64, threshold: f64) -> f64 {
+	match x.abs() > threshold {
+		true => x - threshold * x.signum(),
+		false => 0.0,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn penalties() {
+		let elastic = Regularization {
+			penalty: Penalty::ElasticNet,
+			lambda: 2.0,
+			l1_ratio: 0.25,
+		};
+		assert_eq!((elastic.l1(), elastic.l2()), (0.5, 1.5));
+		// Intercept is not penalized
+		let theta = [100.0, 2.0, -4.0];
+		assert_eq!(elastic.cost(&theta), 0.5 * 6.0 + 0.75 * 20.0);
+		let mut gradient = vec![1.0, 1.0, 1.0];
+		elastic.add_gradient(&theta, &mut gradient);
+		assert_eq!(gradient, vec![1.0, 4.0, -5.0]);
+
+		let lasso = Regularization {
+			penalty: Penalty::Lasso,
+			lambda: 1.0,
+			..elastic
+		};
+		assert_eq!((lasso.l1(), lasso.l2()), (1.0, 0.0));
+		let mut theta = vec![100.0, 2.0, -0.5];
+		let diff = lasso.shrink(&mut theta, 1.0);
+		assert_eq!(theta, vec![100.0, 1.0, 0.0]);
+		assert_eq!(diff, vec![0.0, 1.0, -0.5]);
+
+		assert!(Regularization::default().is_none());
+		assert!(Regularization {
+			penalty: Penalty::Ridge,
+			lambda: 0.0,
+			..elastic
+		}
+		.is_none());
+		assert!(Regularization {
+			lambda: -1.0,
+			..elastic
+		}
+		.validate()
+		.is_err());
+		assert!(Regularization {
+			l1_ratio: 1.5,
+			..elastic
+		}
+		.validate()
+		.is_err());
+		assert_eq!("elastic-net".parse(), Ok(Penalty::ElasticNet));
+		assert_eq!(Penalty::Ridge.to_string(), "ridge");
+	}
+}
@@ -0,0 +1,131 @@
+//! Utility mod for error handling
+use std::fmt;
+use std::io;
+
+use crate::missing::Invalid;
+
+pub type Result<T> = std::result::Result<T, TrainError>;
+
+#[derive(Debug)]
+pub enum TrainError {
+	Io(io::Error),
+	/// Malformed CSV content
+	Csv {
+		line: usize,
+		column: usize,
+		message: String,
+	},
+	/// Cell that can't be used as a number
+	InvalidValue {
+		line: usize,
+		column: usize,
+		value: String,
+		reason: Invalid,
+	},
+	/// Sample weight below zero
+	NegativeWeight {
+		line: usize,
+		column: usize,
+		value: f64,
+	},
+	/// Selected column is not present in the dataset
+	UnknownColumn {
+		column: String,
+		available: Vec<String>,
+	},
+	/// Closed-form solution doesn't exist, features are collinear
+	Singular,
+	/// Gradient descent increased the cost, learning rate is too large
+	Diverged {
+		epoch: usize,
+		learning_rate: f64,
+		cost: f64,
+	},
+	/// Key outside the training range, refused by [`Extrapolation::Refuse`](crate::domain::Extrapolation::Refuse)
+	OutOfDomain {
+		/// Line of the batch input
+		line: Option<usize>,
+		feature: String,
+		value: f64,
+		min: f64,
+		max: f64,
+	},
+	Custom(String),
+}
+
+impl fmt::Display for TrainError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			TrainError::Io(ref err) => err.fmt(f),
+			TrainError::Csv {
+				line,
+				column,
+				ref message,
+			} => write!(f, "line {}, column {}: {}", line, column, message),
+			TrainError::InvalidValue {
+				line,
+				column,
+				ref value,
+				reason,
+			} => write!(
+				f,
+				"line {}, column {}: {} value {:?}",
+				line, column, reason, value
+			),
+			TrainError::NegativeWeight {
+				line,
+				column,
+				value,
+			} => write!(
+				f,
+				"line {}, column {}: negative weight {}",
+				line, column, value
+			),
+			TrainError::UnknownColumn {
+				ref column,
+				ref available,
+			} => write!(
+				f,
+				"Column {} not found, available columns: {}",
+				column,
+				available.join(", ")
+			),
+			TrainError::Singular => write!(
+				f,
+				"Design matrix is singular, features are collinear or constant"
+			),
+			TrainError::Diverged {
+				epoch,
+				learning_rate,
+				cost,
+			} => write!(
+				f,
+				"Gradient descent diverged in epoch {} with learning rate {} (cost {})",
+				epoch, learning_rate, cost
+			),
+			TrainError::OutOfDomain {
+				line,
+				ref feature,
+				value,
+				min,
+				max,
+			} => {
+				if let Some(line) = line {
+					write!(f, "line {}: ", line)?;
+				}
+				write!(
+					f,
+					"Key {} [{}] is outside the training range {} to {}",
+					value, feature, min, max
+				)
+			}
+			TrainError::Custom(ref err) => write!(f, "Custom Error: {:?}", err),
+		}
+	}
+}
+
+impl From<io::Error> for TrainError {
+	fn from(f: io::Error) -> Self {
+		Self::Io(f)
+	}
+}
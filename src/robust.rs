@@ -0,0 +1,277 @@
+//! Robust estimators, fits that ignore outliers instead of averaging them in
+//!
+//! - RANSAC fits exact lines through random minimal subsets of the samples, keeps the
+//!   one with most samples within `threshold` of it, the inliers, and refits least
+//!   squares on these. Works with any number of features.
+//! - Theil–Sen takes the median of the slopes between pairs of samples and the median
+//!   intercept, single feature only. Stays put with up to ~29% of outliers.
+use rand::prelude::*;
+
+use crate::dataset::Sample;
+use crate::metrics::weighted_median;
+use crate::model::dot;
+use crate::result::{Result, TrainError};
+use crate::solver;
+
+/// Number of sample pairs above which Theil–Sen uses a random subset of them
+const MAX_PAIRS: usize = 1_000_000;
+
+/// Settings of the RANSAC fit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ransac {
+	/// Largest absolute residual of an inlier, in target units
+	///
+	/// `Option::None` means the median absolute deviation of the target.
+	pub threshold: Option<f64>,
+	/// Number of random subsets tried
+	pub trials: usize,
+}
+
+impl Default for Ransac {
+	fn default() -> Self {
+		Self {
+			threshold: Option::None,
+			trials: 100,
+		}
+	}
+}
+
+/// Result of the RANSAC fit
+#[derive(Debug, Clone, PartialEq)]
+pub struct RansacFit {
+	/// Least squares theta of the inliers
+	pub theta: Vec<f64>,
+	/// Inlier flag per sample, in the order of the fitted samples
+	pub inliers: Vec<bool>,
+	/// Effective `Ransac::threshold`
+	pub threshold: f64,
+}
+
+impl RansacFit {
+	/// Number of inliers
+	pub fn inlier_count(&self) -> usize {
+		self.inliers.iter().filter(|i| **i).count()
+	}
+}
+
+/// Weighted median absolute deviation of the targets
+pub fn target_mad(samples: &[Sample]) -> f64 {
+	let center = weighted_median(
+		&mut samples
+			.iter()
+			.map(|s| (s.target, s.weight))
+			.collect::<Vec<_>>(),
+	);
+	weighted_median(
+		&mut samples
+			.iter()
+			.map(|s| ((s.target - center).abs(), s.weight))
+			.collect::<Vec<_>>(),
+	)
+}
+
+/// Random sample consensus
+///
+/// Every trial solves the line through `features + 1` random samples and sums the
+/// weights of the samples within the threshold, ties are broken by the smaller squared
+/// error of the inliers. Subsets with collinear features are skipped.
+pub fn ransac(samples: &[Sample], settings: &Ransac, seed: u64) -> Result<RansacFit> {
+	let subset_len = match samples.first() {
+		Some(sample) => sample.features.len() + 1,
+		None => {
+			return Err(TrainError::Custom(String::from(
+				"RANSAC needs at least one training sample",
+			)))
+		}
+	};
+	if samples.len() < subset_len {
+		return Err(TrainError::Custom(format!(
+			"RANSAC needs at least {} samples, got {}",
+			subset_len,
+			samples.len()
+		)));
+	}
+	let threshold = settings.threshold.unwrap_or_else(|| target_mad(samples));
+	let mut r = StdRng::seed_from_u64(seed);
+	// Inlier flags, weight and squared error of the best trial
+	let mut best: Option<(Vec<bool>, f64, f64)> = Option::None;
+	for _ in 0..settings.trials {
+		let subset: Vec<Sample> = rand::seq::index::sample(&mut r, samples.len(), subset_len)
+			.iter()
+			.map(|i| samples[i].clone())
+			.collect();
+		let theta = match solver::qr(&subset, subset_len - 1) {
+			Ok(theta) if theta.iter().all(|t| t.is_finite()) => theta,
+			_ => continue,
+		};
+		let residuals: Vec<f64> = samples
+			.iter()
+			.map(|s| s.target - dot(&theta, &s.features))
+			.collect();
+		let inliers: Vec<bool> = residuals.iter().map(|r| r.abs() <= threshold).collect();
+		let (weight, error) = samples
+			.iter()
+			.zip(residuals.iter().zip(inliers.iter()))
+			.filter(|(_, (_, i))| **i)
+			.fold((0.0, 0.0), |(weight, error), (s, (r, _))| {
+				(weight + s.weight, error + s.weight * r * r)
+			});
+		let better = match &best {
+			Some((_, best_weight, best_error)) => {
+				weight > *best_weight || (weight == *best_weight && error < *best_error)
+			}
+			None => true,
+		};
+		if better {
+			best = Some((inliers, weight, error));
+		}
+	}
+	let (inliers, _, _) = best.ok_or_else(|| {
+		TrainError::Custom(format!(
+			"RANSAC found no subset with independent features in {} trials",
+			settings.trials
+		))
+	})?;
+	let consensus: Vec<Sample> = samples
+		.iter()
+		.zip(inliers.iter())
+		.filter(|(_, i)| **i)
+		.map(|(s, _)| s.clone())
+		.collect();
+	Ok(RansacFit {
+		theta: solver::qr(&consensus, subset_len - 1)?,
+		inliers,
+		threshold,
+	})
+}
+
+/// Theil–Sen estimator of a single feature
+///
+/// Slope is the median of the slopes between all pairs of samples with distinct
+/// feature values, or between `1_000_000` random pairs for larger datasets. The
+/// intercept is the median of `y - slope * x`. Pairs weigh the product of the
+/// sample weights.
+pub fn theil_sen(samples: &[Sample], seed: u64) -> Result<Vec<f64>> {
+	if let Some(sample) = samples.iter().find(|s| s.features.len() != 1) {
+		return Err(TrainError::Custom(format!(
+			"Theil-Sen needs a single feature, got {}",
+			sample.features.len()
+		)));
+	}
+	let m = samples.len();
+	if m < 2 {
+		return Err(TrainError::Custom(format!(
+			"Theil-Sen needs at least 2 samples, got {}",
+			m
+		)));
+	}
+	let slope = |(i, j): (usize, usize)| {
+		let (a, b) = (&samples[i], &samples[j]);
+		match a.features[0] != b.features[0] {
+			true => Some((
+				(b.target - a.target) / (b.features[0] - a.features[0]),
+				a.weight * b.weight,
+			)),
+			false => None,
+		}
+	};
+	let mut slopes: Vec<(f64, f64)> = match m * m.saturating_sub(1) / 2 <= MAX_PAIRS {
+		true => (0..m)
+			.flat_map(|i| (i + 1..m).map(move |j| (i, j)))
+			.filter_map(slope)
+			.collect(),
+		false => {
+			let mut r = StdRng::seed_from_u64(seed);
+			(0..MAX_PAIRS)
+				.map(|_| (r.gen_range(0..m), r.gen_range(0..m)))
+				.filter_map(slope)
+				.collect()
+		}
+	};
+	if slopes.is_empty() {
+		return Err(TrainError::Singular);
+	}
+	let slope = weighted_median(&mut slopes);
+	let intercept = weighted_median(
+		&mut samples
+			.iter()
+			.map(|s| (s.target - slope * s.features[0], s.weight))
+			.collect::<Vec<_>>(),
+	);
+	Ok(vec![intercept, slope])
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// `y = 3 + 2x` with every fifth target off by a lot
+	fn contaminated() -> Vec<Sample> {
+		(0..40)
+			.map(|i| {
+				let x = i as f64;
+				let noise = ((i * 7) % 5) as f64 * 0.1 - 0.2;
+				let y = match i % 5 {
+					0 => 500.0 - 3.0 * x,
+					_ => 3.0 + 2.0 * x + noise,
+				};
+				Sample::new(vec![x], y)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn ransac_inliers() {
+		let samples = contaminated();
+		let fit = ransac(
+			&samples,
+			&Ransac {
+				threshold: Some(1.0),
+				trials: 50,
+			},
+			7,
+		)
+		.unwrap();
+		for (i, inlier) in fit.inliers.iter().enumerate() {
+			assert_eq!(*inlier, i % 5 != 0, "sample {}", i);
+		}
+		assert_eq!(fit.inlier_count(), 32);
+		assert!((fit.theta[0] - 3.0).abs() < 0.2 && (fit.theta[1] - 2.0).abs() < 0.01);
+		// Same seed, same fit
+		assert_eq!(
+			ransac(
+				&samples,
+				&Ransac {
+					threshold: Some(1.0),
+					trials: 50
+				},
+				7
+			)
+			.unwrap(),
+			fit
+		);
+		// Too few samples for a line
+		assert!(ransac(&samples[..1], &Ransac::default(), 7).is_err());
+		assert!(ransac(&[], &Ransac::default(), 7).is_err());
+	}
+
+	#[test]
+	fn theil_sen_slope() {
+		let samples = contaminated();
+		let theta = theil_sen(&samples, 7).unwrap();
+		assert!(
+			(theta[0] - 3.0).abs() < 0.5 && (theta[1] - 2.0).abs() < 0.05,
+			"{:?}",
+			theta
+		);
+		let exact: Vec<Sample> = [(1.0, 1.0), (2.0, 3.0), (4.0, 7.0)]
+			.iter()
+			.map(|(x, y)| Sample::new(vec![*x], *y))
+			.collect();
+		assert_eq!(theil_sen(&exact, 7).unwrap(), vec![-1.0, 2.0]);
+		assert!(theil_sen(&exact[..1], 7).is_err());
+		assert!(theil_sen(&[], 7).is_err());
+		assert!(theil_sen(&[Sample::new(vec![1.0, 2.0], 1.0)], 7).is_err());
+		assert_eq!(target_mad(&exact), 2.0);
+	}
+}
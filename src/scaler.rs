@@ -0,0 +1,251 @@
+//! Feature and target scaling used by the gradient descent
+//!
+//! Every column is scaled as `x' = (x - offset) / scale`. The gradient descent fits
+//! theta on the scaled data, which is then folded back into theta for the raw data,
+//! so the saved model works on raw values without knowing about the scaling:
+//! - `theta[j] = scale_y * theta'[j] / scale_j`
+//! - `theta[0] = offset_y + scale_y * (theta'[0] - Sum(j)(theta'[j] * offset_j / scale_j))`
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::metrics::quantile;
+
+/// Scaling method
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Scaler {
+	/// Values are used as they are
+	None,
+	/// `(x - min) / (max - min)`, scales into `[0, 1]`
+	#[default]
+	MinMax,
+	/// `(x - mean) / std`
+	ZScore,
+	/// `(x - median) / IQR`, not affected by outliers
+	Robust,
+}
+
+impl Scaler {
+	/// Fits the scaling parameters on the values of a column
+	pub fn fit(&self, values: &[f64]) -> Scaling {
+		if values.is_empty() {
+			return Scaling::IDENTITY;
+		}
+		let n = values.len() as f64;
+		let (offset, scale) = match self {
+			Scaler::None => (0.0, 1.0),
+			Scaler::MinMax => {
+				let min = values.iter().cloned().fold(f64::MAX, f64::min);
+				let max = values.iter().cloned().fold(f64::MIN, f64::max);
+				(min, max - min)
+			}
+			Scaler::ZScore => {
+				let mean = values.iter().sum::<f64>() / n;
+				let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+				(mean, var.sqrt())
+			}
+			Scaler::Robust => {
+				let mut sorted = values.to_vec();
+				sorted.sort_by(|a, b| a.total_cmp(b));
+				(
+					quantile(&sorted, 0.5),
+					quantile(&sorted, 0.75) - quantile(&sorted, 0.25),
+				)
+			}
+		};
+		// Constant column can't be scaled, only shifted
+		match scale > 0.0 && scale.is_finite() {
+			true => Scaling {
+				method: *self,
+				offset,
+				scale,
+			},
+			false => Scaling {
+				method: *self,
+				offset,
+				scale: 1.0,
+			},
+		}
+	}
+}
+
+/// Accepts `none`, `minmax`, `zscore` and `robust`
+impl FromStr for Scaler {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(Scaler::None),
+			"minmax" => Ok(Scaler::MinMax),
+			"zscore" => Ok(Scaler::ZScore),
+			"robust" => Ok(Scaler::Robust),
+			_ => Err(format!("Unknown scaler {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Scaler {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Scaler::None => write!(f, "none"),
+			Scaler::MinMax => write!(f, "minmax"),
+			Scaler::ZScore => write!(f, "zscore"),
+			Scaler::Robust => write!(f, "robust"),
+		}
+	}
+}
+
+/// Fitted scaling of a single column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaling {
+	pub method: Scaler,
+	pub offset: f64,
+	pub scale: f64,
+}
+
+impl Scaling {
+	/// Scaling that keeps values as they are
+	pub const IDENTITY: Scaling = Scaling {
+		method: Scaler::None,
+		offset: 0.0,
+		scale: 1.0,
+	};
+
+	/// Raw value to scaled
+	pub fn apply(&self, x: f64) -> f64 {
+		(x - self.offset) / self.scale
+	}
+
+	/// Scaled value to raw
+	pub fn revert(&self, x: f64) -> f64 {
+		x * self.scale + self.offset
+	}
+}
+
+/// Fitted scaling of all features and the target
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleScaling {
+	pub features: Vec<Scaling>,
+	pub target: Scaling,
+}
+
+impl SampleScaling {
+	/// Fits the scalers on the samples
+	pub fn fit(samples: &[Sample], features: Scaler, target: Scaler) -> Self {
+		let feature_count = samples.first().map_or(0, |s| s.features.len());
+		Self {
+			features: (0..feature_count)
+				.map(|j| {
+					let column: Vec<f64> = samples.iter().map(|s| s.features[j]).collect();
+					features.fit(&column)
+				})
+				.collect(),
+			target: target.fit(&samples.iter().map(|s| s.target).collect::<Vec<_>>()),
+		}
+	}
+
+	/// Scaled copy of the sample, of the same weight
+	pub fn apply(&self, sample: &Sample) -> Sample {
+		Sample::weighted(
+			sample
+				.features
+				.iter()
+				.zip(self.features.iter())
+				.map(|(x, scaling)| scaling.apply(*x))
+				.collect(),
+			self.target.apply(sample.target),
+			sample.weight,
+		)
+	}
+
+	/// Theta fitted on scaled samples to theta for raw samples
+	pub fn theta_to_raw(&self, theta: &[f64]) -> Vec<f64> {
+		let y = self.target;
+		let mut intercept = theta[0];
+		let mut raw = vec![0.0; theta.len()];
+		for ((r, t), x) in raw[1..]
+			.iter_mut()
+			.zip(theta[1..].iter())
+			.zip(self.features.iter())
+		{
+			*r = y.scale * t / x.scale;
+			intercept -= t * x.offset / x.scale;
+		}
+		raw[0] = y.revert(intercept);
+		raw
+	}
+
+	/// Theta for raw samples to theta for scaled samples, inverse of [`SampleScaling::theta_to_raw`]
+	pub fn theta_from_raw(&self, raw: &[f64]) -> Vec<f64> {
+		let y = self.target;
+		let mut intercept = raw[0];
+		let mut theta = vec![0.0; raw.len()];
+		for ((t, r), x) in theta[1..]
+			.iter_mut()
+			.zip(raw[1..].iter())
+			.zip(self.features.iter())
+		{
+			*t = r * x.scale / y.scale;
+			intercept += r * x.offset;
+		}
+		theta[0] = y.apply(intercept);
+		theta
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::model::dot;
+
+	#[test]
+	fn fit() {
+		let values = [1.0, 2.0, 3.0, 4.0, 100.0];
+		assert_eq!(
+			Scaler::MinMax.fit(&values),
+			Scaling {
+				method: Scaler::MinMax,
+				offset: 1.0,
+				scale: 99.0
+			}
+		);
+		assert_eq!(
+			Scaler::Robust.fit(&values),
+			Scaling {
+				method: Scaler::Robust,
+				offset: 3.0,
+				scale: 2.0
+			}
+		);
+		let z = Scaler::ZScore.fit(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+		assert_eq!((z.offset, z.scale), (5.0, 2.0));
+		assert_eq!(Scaler::None.fit(&values), Scaling::IDENTITY);
+		assert_eq!(Scaler::MinMax.fit(&[3.0, 3.0]).scale, 1.0);
+	}
+
+	#[test]
+	fn theta_folding() {
+		let samples: Vec<Sample> = (0..10)
+			.map(|i| {
+				Sample::new(
+					vec![i as f64 * 3.0 + 20.0, (i * i) as f64],
+					i as f64 * 7.0 - 2.0,
+				)
+			})
+			.collect();
+		for scaler in [Scaler::None, Scaler::MinMax, Scaler::ZScore, Scaler::Robust] {
+			let scaling = SampleScaling::fit(&samples, scaler, scaler);
+			let theta = [0.3, -1.5, 2.0];
+			let raw = scaling.theta_to_raw(&theta);
+			for sample in samples.iter() {
+				let scaled = scaling.apply(sample);
+				let expected = scaling.target.revert(dot(&theta, &scaled.features));
+				assert!((dot(&raw, &sample.features) - expected).abs() < 1e-9);
+			}
+			let back = scaling.theta_from_raw(&raw);
+			for (a, b) in back.iter().zip(theta.iter()) {
+				assert!((a - b).abs() < 1e-12, "{:?}", back);
+			}
+		}
+	}
+}
@@ -0,0 +1,204 @@
+//! Learning rate schedules
+//!
+//! The trainer asks for the learning rate *alpha* at the start of every iteration (epoch).
+//! An optional linear warm-up ramps alpha up during the first epochs, the decay
+//! is then applied counting epochs from the end of the warm-up.
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::params::Spec;
+
+/// Decay of the learning rate over epochs, `t` being the epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Decay {
+	/// `alpha`
+	#[default]
+	Constant,
+	/// `alpha * factor ^ floor(t / every)`
+	Step { factor: f64, every: usize },
+	/// `alpha * e ^ (-rate * t)`
+	Exponential { rate: f64 },
+	/// `alpha / (1 + rate * t)`
+	InverseTime { rate: f64 },
+	/// Half cosine from `alpha` down to `min_alpha` over `epochs`, then `min_alpha`
+	Cosine { epochs: usize, min_alpha: f64 },
+}
+
+impl Decay {
+	/// Learning rate at epoch `t`
+	pub fn alpha(&self, alpha: f64, t: usize) -> f64 {
+		let t_f = t as f64;
+		match *self {
+			Decay::Constant => alpha,
+			Decay::Step { factor, every } => alpha * factor.powi((t / every.max(1)) as i32),
+			Decay::Exponential { rate } => alpha * (-rate * t_f).exp(),
+			Decay::InverseTime { rate } => alpha / (1.0 + rate * t_f),
+			Decay::Cosine { epochs, min_alpha } => {
+				let progress = t_f.min(epochs as f64) / epochs.max(1) as f64;
+				min_alpha + 0.5 * (alpha - min_alpha) * (1.0 + (PI * progress).cos())
+			}
+		}
+	}
+}
+
+/// Learning rate schedule, decay with optional warm-up
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Schedule {
+	pub decay: Decay,
+	/// Number of epochs over which alpha linearly grows to its initial value
+	pub warmup: usize,
+}
+
+impl Schedule {
+	/// Learning rate for given epoch, `alpha` being the initial learning rate
+	pub fn alpha(&self, alpha: f64, epoch: usize) -> f64 {
+		match epoch < self.warmup {
+			true => alpha * (epoch + 1) as f64 / self.warmup as f64,
+			false => self.decay.alpha(alpha, epoch - self.warmup),
+		}
+	}
+}
+
+/// Non-negative parameter, e.g. a decay rate
+fn non_negative<'a>(spec: &Spec<'a>, key: &'a str, default: f64) -> Result<f64, String> {
+	match spec.get(key, default) {
+		value if value >= 0.0 && value.is_finite() => Ok(value),
+		value => Err(format!(
+			"Invalid {} {} {}, expected at least 0",
+			spec.name, key, value
+		)),
+	}
+}
+
+/// Number of epochs parameter, a positive integer
+fn epochs<'a>(spec: &Spec<'a>, key: &'a str, default: f64) -> Result<usize, String> {
+	match spec.get(key, default) {
+		value if value >= 1.0 && value.fract() == 0.0 && value.is_finite() => Ok(value as usize),
+		value => Err(format!(
+			"Invalid {} {} {}, expected a positive integer",
+			spec.name, key, value
+		)),
+	}
+}
+
+/// Accepts `name[:param=value,...]`
+///
+/// - `constant`
+/// - `step:factor=0.5,every=100`
+/// - `exponential:rate=0.001`
+/// - `inverse-time:rate=0.01`
+/// - `cosine:epochs=1000,min_alpha=0`
+///
+/// Values shown are the defaults of omitted parameters. Rates and `min_alpha` must be
+/// at least 0, `factor` in `(0, 1]`, `every` and `epochs` positive integers.
+impl FromStr for Decay {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let spec = Spec::parse(s)?;
+		let decay = match spec.name {
+			"constant" => Decay::Constant,
+			"step" => Decay::Step {
+				factor: match spec.get("factor", 0.5) {
+					factor if factor > 0.0 && factor <= 1.0 => factor,
+					factor => {
+						return Err(format!("Invalid step factor {}, expected 0 to 1", factor))
+					}
+				},
+				every: epochs(&spec, "every", 100.0)?,
+			},
+			"exponential" => Decay::Exponential {
+				rate: non_negative(&spec, "rate", 0.001)?,
+			},
+			"inverse-time" => Decay::InverseTime {
+				rate: non_negative(&spec, "rate", 0.01)?,
+			},
+			"cosine" => Decay::Cosine {
+				epochs: epochs(&spec, "epochs", 1000.0)?,
+				min_alpha: non_negative(&spec, "min_alpha", 0.0)?,
+			},
+			_ => return Err(format!("Unknown schedule {:?}", spec.name)),
+		};
+		spec.finish(decay)
+	}
+}
+
+impl fmt::Display for Decay {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Decay::Constant => write!(f, "constant"),
+			Decay::Step { factor, every } => write!(f, "step:factor={},every={}", factor, every),
+			Decay::Exponential { rate } => write!(f, "exponential:rate={}", rate),
+			Decay::InverseTime { rate } => write!(f, "inverse-time:rate={}", rate),
+			Decay::Cosine { epochs, min_alpha } => {
+				write!(f, "cosine:epochs={},min_alpha={}", epochs, min_alpha)
+			}
+		}
+	}
+}
+
+impl fmt::Display for Schedule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.warmup {
+			0 => write!(f, "{}", self.decay),
+			warmup => write!(f, "{} after {} warm-up epochs", self.decay, warmup),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn close(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-12
+	}
+
+	#[test]
+	fn decays() {
+		let step: Decay = "step:factor=0.5,every=10".parse().unwrap();
+		assert!(close(step.alpha(1.0, 9), 1.0));
+		assert!(close(step.alpha(1.0, 25), 0.25));
+		let exp: Decay = "exponential:rate=0.1".parse().unwrap();
+		assert!(close(exp.alpha(2.0, 10), 2.0 * (-1.0_f64).exp()));
+		let inv: Decay = "inverse-time:rate=0.5".parse().unwrap();
+		assert!(close(inv.alpha(1.0, 2), 0.5));
+		let cos: Decay = "cosine:epochs=100,min_alpha=0.1".parse().unwrap();
+		assert!(close(cos.alpha(1.0, 0), 1.0));
+		assert!(close(cos.alpha(1.0, 50), 0.55));
+		assert!(close(cos.alpha(1.0, 500), 0.1));
+		assert!("linear".parse::<Decay>().is_err());
+		assert!("step:rate=1".parse::<Decay>().is_err());
+	}
+
+	#[test]
+	fn invalid_parameters() {
+		for spec in [
+			"inverse-time:rate=-0.5",
+			"exponential:rate=-1",
+			"step:factor=-1",
+			"step:factor=0",
+			"step:factor=1.5",
+			"step:every=0",
+			"step:every=0.5",
+			"cosine:epochs=0",
+			"cosine:epochs=10.5",
+			"cosine:min_alpha=-0.1",
+		] {
+			assert!(spec.parse::<Decay>().is_err(), "{}", spec);
+		}
+		assert!("step:factor=1,every=1".parse::<Decay>().is_ok());
+		assert!("inverse-time:rate=0".parse::<Decay>().is_ok());
+	}
+
+	#[test]
+	fn warmup() {
+		let schedule = Schedule {
+			decay: Decay::InverseTime { rate: 1.0 },
+			warmup: 4,
+		};
+		let alphas: Vec<f64> = (0..6).map(|e| schedule.alpha(1.0, e)).collect();
+		assert_eq!(alphas, vec![0.25, 0.5, 0.75, 1.0, 1.0, 0.5]);
+	}
+}
@@ -0,0 +1,360 @@
+//! Closed-form ordinary least squares solvers
+//!
+//! Exact alternative to the gradient descent, useful as a reference answer
+//! and for instant fits on small datasets. Both solvers work on the raw features
+//! with an intercept column of ones, the design matrix `X`. Weighted samples have their
+//! rows scaled by the square root of the weight, which makes it weighted least squares
+//! `X^T * W * X * theta = X^T * W * y`. With a ridge penalty
+//! they solve the penalized problem instead, see [`crate::regularization`].
+//! Lasso and elastic net have no closed form and are fitted by coordinate descent.
+//! Fits robust to outliers are in [`crate::robust`].
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Sample;
+use crate::linalg::Matrix;
+use crate::loss::total_weight;
+use crate::regularization::{soft_threshold, Regularization};
+use crate::result::{Result, TrainError};
+
+/// Method used to fit theta
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Solver {
+	/// Iterative gradient descent
+	#[default]
+	GradientDescent,
+	/// Solves the normal equation `X^T * X * theta = X^T * y`
+	NormalEquation,
+	/// Least squares via QR decomposition of `X`, numerically stable
+	Qr,
+	/// Cyclic coordinate descent, handles all penalties
+	CoordinateDescent,
+	/// Least squares of the inliers found by random sample consensus
+	Ransac,
+	/// Median of the pairwise slopes, single feature only
+	TheilSen,
+}
+
+impl Solver {
+	/// Whether the solver can fit the L1 penalty of lasso and elastic net
+	pub fn supports_l1(&self) -> bool {
+		matches!(self, Solver::GradientDescent | Solver::CoordinateDescent)
+	}
+
+	/// Whether the solver can fit a penalized cost at all
+	pub fn supports_penalty(&self) -> bool {
+		!matches!(self, Solver::Ransac | Solver::TheilSen)
+	}
+}
+
+/// Accepts `gd`, `normal-equation`, `qr`, `cd`, `ransac` and `theil-sen`
+impl FromStr for Solver {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"gd" => Ok(Solver::GradientDescent),
+			"normal-equation" => Ok(Solver::NormalEquation),
+			"qr" => Ok(Solver::Qr),
+			"cd" => Ok(Solver::CoordinateDescent),
+			"ransac" => Ok(Solver::Ransac),
+			"theil-sen" => Ok(Solver::TheilSen),
+			_ => Err(format!("Unknown solver {:?}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Solver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Solver::GradientDescent => write!(f, "gd"),
+			Solver::NormalEquation => write!(f, "normal-equation"),
+			Solver::Qr => write!(f, "qr"),
+			Solver::CoordinateDescent => write!(f, "cd"),
+			Solver::Ransac => write!(f, "ransac"),
+			Solver::TheilSen => write!(f, "theil-sen"),
+		}
+	}
+}
+
+/// Design matrix, rows of `[1, x_0, ..., x_n-1]` for `n = feature_count`
+pub fn design_matrix(samples: &[Sample], feature_count: usize) -> Matrix {
+	let mut x = Matrix::zeros(samples.len(), feature_count + 1);
+	for (i, sample) in samples.iter().enumerate() {
+		x[(i, 0)] = 1.0;
+		for (j, value) in sample.features.iter().enumerate() {
+			x[(i, j + 1)] = *value;
+		}
+	}
+	x
+}
+
+/// Target vector
+pub fn targets(samples: &[Sample]) -> Vec<f64> {
+	samples.iter().map(|s| s.target).collect()
+}
+
+/// Design matrix and target vector with rows scaled by `sqrt(weight)`
+pub fn weighted_system(samples: &[Sample], feature_count: usize) -> (Matrix, Vec<f64>) {
+	let mut x = design_matrix(samples, feature_count);
+	for (i, sample) in samples.iter().enumerate() {
+		let scale = sample.weight.sqrt();
+		for j in 0..x.cols() {
+			x[(i, j)] *= scale;
+		}
+	}
+	let y = samples.iter().map(|s| s.target * s.weight.sqrt()).collect();
+	(x, y)
+}
+
+/// Checks there are enough samples to determine theta
+///
+/// Least squares needs a sample per coefficient, the ridge penalty determines
+/// theta from a single sample.
+fn check_samples(samples: &[Sample], feature_count: usize, l2: f64) -> Result<()> {
+	let needed = match l2 > 0.0 {
+		true => 1,
+		false => feature_count + 1,
+	};
+	match samples.len() < needed {
+		true => Err(TrainError::Custom(format!(
+			"Least squares of {} coefficients needs at least {} samples, got {}",
+			feature_count + 1,
+			needed,
+			samples.len()
+		))),
+		false => Ok(()),
+	}
+}
+
+/// Solves the normal equation `X^T * X * theta = X^T * y`
+pub fn normal_equation(samples: &[Sample], feature_count: usize) -> Result<Vec<f64>> {
+	ridge_normal_equation(samples, feature_count, 0.0)
+}
+
+/// Least squares solution using QR decomposition of `X`
+pub fn qr(samples: &[Sample], feature_count: usize) -> Result<Vec<f64>> {
+	ridge_qr(samples, feature_count, 0.0)
+}
+
+/// Solves the ridge normal equation `(X^T * W * X + m * l2 * D) * theta = X^T * W * y`
+///
+/// `D` is the identity without the intercept entry, `m` the sum of the weights and `l2`
+/// the strength of the L2 penalty, see [`Regularization::l2`].
+pub fn ridge_normal_equation(
+	samples: &[Sample],
+	feature_count: usize,
+	l2: f64,
+) -> Result<Vec<f64>> {
+	check_samples(samples, feature_count, l2)?;
+	let (x, y) = weighted_system(samples, feature_count);
+	let xt = x.transpose();
+	let mut xtx = xt.mul(&x);
+	for j in 1..xtx.rows() {
+		xtx[(j, j)] += total_weight(samples) * l2;
+	}
+	xtx.solve(&xt.mul_vec(&y)).ok_or(TrainError::Singular)
+}
+
+/// Ridge solution using QR decomposition of `X` augmented by rows `sqrt(m * l2) * e_j`
+pub fn ridge_qr(samples: &[Sample], feature_count: usize, l2: f64) -> Result<Vec<f64>> {
+	check_samples(samples, feature_count, l2)?;
+	let (mut x, mut y) = weighted_system(samples, feature_count);
+	if l2 > 0.0 {
+		let cols = x.cols();
+		let weight = (total_weight(samples) * l2).sqrt();
+		let mut rows: Vec<Vec<f64>> = (0..x.rows()).map(|i| x.row(i).to_vec()).collect();
+		for j in 1..cols {
+			let mut row = vec![0.0; cols];
+			row[j] = weight;
+			rows.push(row);
+			y.push(0.0);
+		}
+		x = Matrix::from_rows(&rows);
+	}
+	x.lstsq(&y).ok_or(TrainError::Singular)
+}
+
+/// Minimizes the penalized cost by cyclic coordinate descent, starting from `theta`
+///
+/// Works on the weighted covariances of the centered features (covariance updates), so a sweep
+/// costs `O(p^2)` whatever the number of samples. Every sweep sets each coefficient to
+/// its exact minimum with the others fixed, `S(rho_j, l1) / (v_j + l2)` where `S` is the
+/// soft-thresholding, `rho_j` the covariance of feature `j` with the partial residual and
+/// `v_j` its variance, and the intercept to fit the means. Stops when no coefficient changes
+/// by more than `tolerance` in a sweep or after `max_sweeps` sweeps.
+/// Returns theta and the number of sweeps.
+pub fn coordinate_descent(
+	samples: &[Sample],
+	regularization: &Regularization,
+	theta: &[f64],
+	tolerance: f64,
+	max_sweeps: usize,
+) -> (Vec<f64>, usize) {
+	let m = match total_weight(samples) {
+		w if w > 0.0 => w,
+		_ => 1.0,
+	};
+	let p = theta.len() - 1;
+	let means: Vec<f64> = (0..p)
+		.map(|j| {
+			samples
+				.iter()
+				.map(|s| s.weight * s.features[j])
+				.sum::<f64>()
+				/ m
+		})
+		.collect();
+	let mean_y = samples.iter().map(|s| s.weight * s.target).sum::<f64>() / m;
+	let mut gram = Matrix::zeros(p, p);
+	let mut covariance = vec![0.0; p];
+	for sample in samples.iter() {
+		let x: Vec<f64> = sample
+			.features
+			.iter()
+			.zip(means.iter())
+			.map(|(x, mean)| x - mean)
+			.collect();
+		let w = sample.weight / m;
+		for j in 0..p {
+			covariance[j] += w * x[j] * (sample.target - mean_y);
+			for k in 0..p {
+				gram[(j, k)] += w * x[j] * x[k];
+			}
+		}
+	}
+	let (l1, l2) = (regularization.l1(), regularization.l2());
+	let mut theta = theta.to_vec();
+	let mut sweeps = 0;
+	while sweeps < max_sweeps {
+		sweeps += 1;
+		let mut max_change: f64 = 0.0;
+		for j in 0..p {
+			let old = theta[j + 1];
+			let fitted: f64 = gram
+				.row(j)
+				.iter()
+				.zip(theta[1..].iter())
+				.map(|(g, t)| g * t)
+				.sum();
+			let rho = covariance[j] - fitted + gram[(j, j)] * old;
+			let denominator = gram[(j, j)] + l2;
+			theta[j + 1] = match denominator > 0.0 {
+				true => soft_threshold(rho, l1) / denominator,
+				false => 0.0,
+			};
+			max_change = max_change.max((theta[j + 1] - old).abs());
+		}
+		let intercept = mean_y
+			- theta[1..]
+				.iter()
+				.zip(means.iter())
+				.map(|(t, mean)| t * mean)
+				.sum::<f64>();
+		max_change = max_change.max((intercept - theta[0]).abs());
+		theta[0] = intercept;
+		if max_change <= tolerance {
+			break;
+		}
+	}
+	(theta, sweeps)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn exact_fit() {
+		// y = 1 + 2a + 3b
+		let samples: Vec<Sample> = (0..12)
+			.map(|i| {
+				let (a, b) = ((i % 4) as f64 + 10.0, (i / 4) as f64 * 0.5);
+				Sample::new(vec![a, b], 1.0 + 2.0 * a + 3.0 * b)
+			})
+			.collect();
+		for theta in [normal_equation(&samples, 2), qr(&samples, 2)] {
+			let theta = theta.unwrap();
+			for (t, expected) in theta.iter().zip([1.0, 2.0, 3.0].iter()) {
+				assert!((t - expected).abs() < 1e-9, "{:?}", theta);
+			}
+		}
+	}
+
+	#[test]
+	fn collinear() {
+		let samples: Vec<Sample> = (0..5)
+			.map(|i| Sample::new(vec![i as f64, 2.0 * i as f64], i as f64))
+			.collect();
+		assert!(matches!(
+			normal_equation(&samples, 2),
+			Err(TrainError::Singular)
+		));
+		assert!(matches!(qr(&samples, 2), Err(TrainError::Singular)));
+		// Ridge penalty makes the collinear problem solvable
+		assert!(ridge_normal_equation(&samples, 2, 0.1).is_ok());
+		assert!(ridge_qr(&samples, 2, 0.1).is_ok());
+	}
+
+	#[test]
+	fn too_few_samples() {
+		let samples = vec![
+			Sample::new(vec![1.0, 2.0], 3.0),
+			Sample::new(vec![2.0, 1.0], 4.0),
+		];
+		for n in [0, 2] {
+			assert!(matches!(
+				normal_equation(&samples[..n], 2),
+				Err(TrainError::Custom(_))
+			));
+			assert!(matches!(qr(&samples[..n], 2), Err(TrainError::Custom(_))));
+		}
+		assert!(ridge_qr(&[], 2, 0.1).is_err());
+		assert_eq!(ridge_qr(&samples, 2, 0.1).unwrap().len(), 3);
+		assert_eq!(design_matrix(&[], 2).cols(), 3);
+	}
+
+	#[test]
+	fn penalized() {
+		use crate::regularization::Penalty;
+
+		// y = 1 + 2a + 3b on centered features
+		let samples: Vec<Sample> = (0..12)
+			.map(|i| {
+				let (a, b) = ((i % 4) as f64 - 1.5, (i / 4) as f64 - 1.0);
+				Sample::new(vec![a, b], 1.0 + 2.0 * a + 3.0 * b)
+			})
+			.collect();
+		let ridge = Regularization {
+			penalty: Penalty::Ridge,
+			lambda: 0.5,
+			l1_ratio: 0.5,
+		};
+		let normal = ridge_normal_equation(&samples, 2, ridge.l2()).unwrap();
+		let qr = ridge_qr(&samples, 2, ridge.l2()).unwrap();
+		let (cd, _) = coordinate_descent(&samples, &ridge, &[0.0; 3], 1e-12, 10_000);
+		// Features are centered and uncorrelated, so ridge divides by 1 + l2 / var
+		let expected = [
+			1.0,
+			2.0 * 1.25 / 1.75,
+			3.0 * (2.0 / 3.0) / (2.0 / 3.0 + 0.5),
+		];
+		for theta in [normal, qr, cd] {
+			for (t, e) in theta.iter().zip(expected.iter()) {
+				assert!((t - e).abs() < 1e-9, "{:?}", theta);
+			}
+		}
+
+		// Lasso subtracts lambda from the covariance and zeroes the weak feature
+		let lasso = Regularization {
+			penalty: Penalty::Lasso,
+			lambda: 2.2,
+			..ridge
+		};
+		let (theta, sweeps) = coordinate_descent(&samples, &lasso, &[0.0; 3], 1e-12, 10_000);
+		assert!(sweeps < 10_000);
+		assert!((theta[0] - 1.0).abs() < 1e-9);
+		assert!((theta[1] - (2.5 - 2.2) / 1.25).abs() < 1e-9, "{:?}", theta);
+		assert_eq!(theta[2], 0.0);
+	}
+}
@@ -1,7 +1,24 @@
 //! Helper module that handles command line arguments
-use clap::{crate_authors, crate_name, crate_version};
+use std::num::NonZeroUsize;
+
+use clap::{crate_name, crate_version};
 use clap::{App, Arg, ArgMatches};
 
+use ft_linear_regression::csv::{self, CsvOptions};
+use ft_linear_regression::dataset::{Column, LoadOptions, Split};
+use ft_linear_regression::loss::LossKind;
+use ft_linear_regression::missing::{Imputation, MissingPolicy};
+use ft_linear_regression::model_io::Format;
+use ft_linear_regression::optimizer::{Batching, Divergence, OptimizerKind};
+use ft_linear_regression::path::{LambdaRule, PathSettings};
+use ft_linear_regression::regularization::{Penalty, Regularization};
+use ft_linear_regression::robust::Ransac;
+use ft_linear_regression::scaler::Scaler;
+use ft_linear_regression::schedule::{Decay, Schedule};
+use ft_linear_regression::solver::Solver;
+use ft_linear_regression::validation::{CrossValidation, Folds};
+use ft_linear_regression::TrainerContext;
+
 pub struct CmdArgs<'a> {
 	pub matches: ArgMatches<'a>,
 }
@@ -10,7 +27,7 @@ impl<'a> CmdArgs<'a> {
 	/// Default constructor
 	pub fn new() -> Self {
 		let matches = App::new(crate_name!())
-		.author(crate_authors!("\n"))
+		.author(env!("CARGO_PKG_AUTHORS"))
 		.version(crate_version!())
 		.arg(
 			Arg::with_name("datafile")
@@ -20,6 +37,59 @@ impl<'a> CmdArgs<'a> {
 				.help("Input data file")
 				.required(true),
 		)
+		.arg(
+			Arg::with_name("x")
+				.long("x")
+				.takes_value(true)
+				.conflicts_with("features")
+				.help("Feature column, header name or zero-based index [default: 0]"),
+		)
+		.arg(
+			Arg::with_name("features")
+				.long("features")
+				.takes_value(true)
+				.multiple(true)
+				.use_delimiter(true)
+				.help("Comma separated feature columns for multivariate regression, header names or zero-based indices"),
+		)
+		.arg(
+			Arg::with_name("y")
+				.long("y")
+				.takes_value(true)
+				.help("Target column, header name or zero-based index [default: 1]"),
+		)
+		.arg(
+			Arg::with_name("weight")
+				.long("weight")
+				.takes_value(true)
+				.help("Column of the sample weights, header name or zero-based index. Negative weights are rejected, rows of zero weight ignored [default: all weigh 1]"),
+		)
+		.arg(
+			Arg::with_name("delimiter")
+				.short("d")
+				.long("delimiter")
+				.takes_value(true)
+				.validator(|d| csv::parse_delimiter(&d).map(|_| ()))
+				.help("Field delimiter of the input data file, single character or \"tab\" [default: ,]"),
+		)
+		.arg(
+			Arg::with_name("missing")
+				.long("missing")
+				.takes_value(true)
+				.possible_values(&["fail", "skip", "impute"])
+				.help("Handling of rows with missing or invalid values (empty, NA, non-numeric, inf) [default: fail]"),
+		)
+		.arg(
+			Arg::with_name("impute")
+				.long("impute")
+				.takes_value(true)
+				.help("Value used by \"--missing impute\", mean, median or a constant [default: mean]"),
+		)
+		.arg(
+			Arg::with_name("noheader")
+				.long("no-header")
+				.help("Input data file has no header line, columns can be selected by index only"),
+		)
 		.arg(
 			Arg::with_name("seed")
 				.short("s")
@@ -34,6 +104,12 @@ impl<'a> CmdArgs<'a> {
 				.takes_value(true)
 				.help("Distribution between test and train set ratio"),
 		)
+		.arg(
+			Arg::with_name("split")
+				.long("split")
+				.takes_value(true)
+				.help("Train and test split, random, stratified:bins=<n> on the target or chronological with the last rows as test set [default: random]"),
+		)
 		.arg(
 			Arg::with_name("outfile")
 				.short("o")
@@ -41,6 +117,13 @@ impl<'a> CmdArgs<'a> {
 				.takes_value(true)
 				.help("Path to output file (model)"),
 		)
+		.arg(
+			Arg::with_name("modelformat")
+				.long("model-format")
+				.takes_value(true)
+				.possible_values(&["json", "legacy", "binary"])
+				.help("Format of the output file, versioned JSON with metadata, the legacy two line text or compact binary with checksum [default: json]")
+		)
 		.arg(
 			Arg::with_name("alpha")
 				.short("a")
@@ -59,7 +142,153 @@ impl<'a> CmdArgs<'a> {
 				.short("i")
 				.long("iterations")
 				.takes_value(true)
-				.help("Number of iterations to run, this will overwrite TD limit")
+				.help("Number of iterations (epochs) to run, this will overwrite TD limit")
+		)
+		.arg(
+			Arg::with_name("optimizer")
+				.long("optimizer")
+				.takes_value(true)
+				.help("Gradient descent update rule, gd, momentum, nesterov, adagrad, rmsprop or adam. Hyperparameters follow as name:param=value,... e.g. adam:beta1=0.9,beta2=0.999,epsilon=1e-8 [default: gd]")
+		)
+		.arg(
+			Arg::with_name("loss")
+				.long("loss")
+				.takes_value(true)
+				.help("Loss of the residuals minimized by the gradient descent, squared, absolute, huber, log-cosh or quantile. Parameters follow as name:param=value,... e.g. huber:delta=1 or quantile:tau=0.9 [default: squared]")
+		)
+		.arg(
+			Arg::with_name("schedule")
+				.long("schedule")
+				.takes_value(true)
+				.help("Learning rate schedule, constant, step, exponential, inverse-time or cosine. Parameters follow as name:param=value,... e.g. step:factor=0.5,every=100 [default: constant]")
+		)
+		.arg(
+			Arg::with_name("warmup")
+				.long("warmup")
+				.takes_value(true)
+				.help("Number of epochs to linearly increase the learning rate to alpha before the schedule applies [default: 0]")
+		)
+		.arg(
+			Arg::with_name("batching")
+				.long("batching")
+				.takes_value(true)
+				.possible_values(&["full", "sgd", "mini-batch"])
+				.help("Gradient descent batching, stochastic modes reshuffle the training set every epoch [default: full]")
+		)
+		.arg(
+			Arg::with_name("batchsize")
+				.long("batch-size")
+				.takes_value(true)
+				.help("Number of samples per update, implies mini-batch [default: 32]")
+		)
+		.arg(
+			Arg::with_name("scale")
+				.long("scale")
+				.takes_value(true)
+				.possible_values(&["none", "minmax", "zscore", "robust"])
+				.help("Feature scaling for the gradient descent and the penalties, robust uses median and interquartile range [default: minmax]")
+		)
+		.arg(
+			Arg::with_name("scaletarget")
+				.long("scale-target")
+				.takes_value(true)
+				.possible_values(&["none", "minmax", "zscore", "robust"])
+				.help("Target scaling for the gradient descent, TD limit then applies to the scaled problem [default: none]")
+		)
+		.arg(
+			Arg::with_name("solver")
+				.long("solver")
+				.takes_value(true)
+				.possible_values(&["gd", "normal-equation", "qr", "cd", "ransac", "theil-sen"])
+				.help("Gradient descent, exact least squares solution by normal equation or QR decomposition, coordinate descent, or the outlier resistant RANSAC and Theil-Sen (single feature) [default: gd]")
+		)
+		.arg(
+			Arg::with_name("ransacthreshold")
+				.long("ransac-threshold")
+				.takes_value(true)
+				.help("Largest residual of a RANSAC inlier, in target units [default: median absolute deviation of the target]")
+		)
+		.arg(
+			Arg::with_name("ransactrials")
+				.long("ransac-trials")
+				.takes_value(true)
+				.help("Number of random subsets fitted by RANSAC [default: 100]")
+		)
+		.arg(
+			Arg::with_name("penalty")
+				.long("penalty")
+				.takes_value(true)
+				.possible_values(&["none", "ridge", "lasso", "elastic-net"])
+				.help("Regularization of the scaled coefficients, never of the intercept. Lasso and elastic-net need the gd or cd solver [default: none]")
+		)
+		.arg(
+			Arg::with_name("lambda")
+				.long("lambda")
+				.takes_value(true)
+				.requires("penalty")
+				.help("\u{03BB} - Strength of the penalty [default: 1]")
+		)
+		.arg(
+			Arg::with_name("l1ratio")
+				.long("l1-ratio")
+				.takes_value(true)
+				.requires("penalty")
+				.help("Share of the L1 penalty in the elastic-net, 0 is ridge and 1 is lasso [default: 0.5]")
+		)
+		.arg(
+			Arg::with_name("path")
+				.long("path")
+				.takes_value(true)
+				.requires("penalty")
+				.conflicts_with("lambda")
+				.help("Fits the penalty along given number of log-spaced lambdas with warm starts and picks lambda by cross-validation, folds of --cv or 5")
+		)
+		.arg(
+			Arg::with_name("pathratio")
+				.long("path-ratio")
+				.takes_value(true)
+				.requires("path")
+				.help("Smallest lambda of the path as a fraction of the largest one, which zeroes all coefficients [default: 0.001]")
+		)
+		.arg(
+			Arg::with_name("lambdarule")
+				.long("lambda-rule")
+				.takes_value(true)
+				.requires("path")
+				.possible_values(&["min", "1se"])
+				.help("Lambda picked from the path, lowest cross-validated error or the largest within one standard error of it [default: 1se]")
+		)
+		.arg(
+			Arg::with_name("divergence")
+				.long("on-divergence")
+				.takes_value(true)
+				.possible_values(&["abort", "backoff"])
+				.help("Reaction to an epoch increasing the cost, abort with an error or roll back and retry with halved learning rate [default: abort]")
+		)
+		.arg(
+			Arg::with_name("divtolerance")
+				.long("divergence-tolerance")
+				.takes_value(true)
+				.help("Relative cost increase per epoch accepted before diverging. Defaults to 1e-9 for full batch gd, other setups 10 over their mean epoch cost")
+		)
+		.arg(
+			Arg::with_name("cv")
+				.long("cv")
+				.takes_value(true)
+				.help("Cross-validates with given number of folds, or leave-one-out for loo, before the training")
+		)
+		.arg(
+			Arg::with_name("cvrepeats")
+				.long("cv-repeats")
+				.takes_value(true)
+				.requires("cv")
+				.help("Number of times the k-fold cross-validation is repeated with a different shuffle [default: 1]")
+		)
+		.arg(
+			Arg::with_name("refit")
+				.long("refit")
+				.requires("cv")
+				.help("Trains the saved model on the full dataset instead of the train set")
 		)
 		.arg(
 			Arg::with_name("tdlimit")
@@ -72,15 +301,283 @@ impl<'a> CmdArgs<'a> {
 		Self { matches }
 	}
 
-	/// Dataset inut file getter
-	///
-	/// Example:
-	/// ```
-	/// let args = CmdArgs::new();
-	/// let dataset_path = args.get_infile();
-	/// dbg!(dataset_path);
-	/// ```
+	/// Dataset input file getter
 	pub fn get_infile(&self) -> &str {
 		self.matches.value_of("datafile").unwrap()
 	}
+
+	/// Feature columns getter, either `--features` or `--x`
+	pub fn get_features(&self) -> Vec<Column> {
+		match self.matches.values_of("features") {
+			Some(values) => values.map(Column::from).collect(),
+			None => vec![Column::from(self.matches.value_of("x").unwrap_or("0"))],
+		}
+	}
+
+	/// Target column getter
+	pub fn get_target(&self) -> Column {
+		Column::from(self.matches.value_of("y").unwrap_or("1"))
+	}
+
+	/// Missing values policy getter, combines `--missing` and `--impute`
+	pub fn get_missing_policy(&self) -> MissingPolicy {
+		let policy = self
+			.matches
+			.value_of("missing")
+			.unwrap_or("fail")
+			.parse::<MissingPolicy>()
+			.expect("Couldn't parse missing value policy");
+		match policy {
+			MissingPolicy::Impute(_) => MissingPolicy::Impute(
+				self.matches
+					.value_of("impute")
+					.unwrap_or("mean")
+					.parse::<Imputation>()
+					.expect("Couldn't parse imputation"),
+			),
+			_ => policy,
+		}
+	}
+
+	/// Batching getter, `--batch-size` implies mini-batch
+	pub fn get_batching(&self) -> Batching {
+		match self.matches.value_of("batchsize") {
+			Some(size) => {
+				Batching::MiniBatch(size.parse::<usize>().expect("Couldn't parse batch size"))
+			}
+			None => self
+				.matches
+				.value_of("batching")
+				.unwrap_or("full")
+				.parse::<Batching>()
+				.expect("Couldn't parse batching"),
+		}
+	}
+
+	/// Learning rate schedule getter, combines `--schedule` and `--warmup`
+	pub fn get_schedule(&self) -> Schedule {
+		Schedule {
+			decay: self
+				.matches
+				.value_of("schedule")
+				.unwrap_or("constant")
+				.parse::<Decay>()
+				.expect("Couldn't parse schedule"),
+			warmup: self
+				.matches
+				.value_of("warmup")
+				.unwrap_or("0")
+				.parse::<usize>()
+				.expect("Couldn't parse warmup"),
+		}
+	}
+
+	/// Regularization getter, combines `--penalty`, `--lambda` and `--l1-ratio`
+	pub fn get_regularization(&self) -> Regularization {
+		Regularization {
+			penalty: self
+				.matches
+				.value_of("penalty")
+				.unwrap_or("none")
+				.parse::<Penalty>()
+				.expect("Couldn't parse penalty"),
+			lambda: self
+				.matches
+				.value_of("lambda")
+				.unwrap_or("1")
+				.parse::<f64>()
+				.expect("Couldn't parse lambda"),
+			l1_ratio: self
+				.matches
+				.value_of("l1ratio")
+				.unwrap_or("0.5")
+				.parse::<f64>()
+				.expect("Couldn't parse l1 ratio"),
+		}
+	}
+
+	/// Regularization path getter, `Option::None` without `--path`
+	pub fn get_path(&self) -> Option<PathSettings> {
+		let settings = PathSettings {
+			count: self
+				.matches
+				.value_of("path")?
+				.parse::<usize>()
+				.expect("Couldn't parse path length"),
+			ratio: self
+				.matches
+				.value_of("pathratio")
+				.unwrap_or("0.001")
+				.parse::<f64>()
+				.expect("Couldn't parse path ratio"),
+			rule: self
+				.matches
+				.value_of("lambdarule")
+				.unwrap_or("1se")
+				.parse::<LambdaRule>()
+				.expect("Couldn't parse lambda rule"),
+		};
+		settings.validate().expect("Couldn't parse path");
+		Some(settings)
+	}
+
+	/// RANSAC settings getter
+	pub fn get_ransac(&self) -> Ransac {
+		Ransac {
+			threshold: self
+				.matches
+				.value_of("ransacthreshold")
+				.map(|s| s.parse::<f64>().expect("Couldn't parse RANSAC threshold")),
+			trials: self
+				.matches
+				.value_of("ransactrials")
+				.unwrap_or("100")
+				.parse::<usize>()
+				.expect("Couldn't parse RANSAC trials"),
+		}
+	}
+
+	/// Cross-validation getter, `Option::None` without `--cv`
+	pub fn get_cross_validation(&self) -> Option<CrossValidation> {
+		Some(CrossValidation {
+			folds: self
+				.matches
+				.value_of("cv")?
+				.parse::<Folds>()
+				.expect("Couldn't parse folds"),
+			repeats: self
+				.matches
+				.value_of("cvrepeats")
+				.unwrap_or("1")
+				.parse::<NonZeroUsize>()
+				.expect("Couldn't parse cross-validation repeats")
+				.get(),
+			refit: self.matches.is_present("refit"),
+		})
+	}
+
+	/// Field delimiter getter, accepts `tab` and `\t` for tab
+	pub fn get_delimiter(&self) -> char {
+		csv::parse_delimiter(self.matches.value_of("delimiter").unwrap_or(","))
+			.expect("Couldn't parse delimiter")
+	}
+}
+
+impl<'a> From<&CmdArgs<'a>> for TrainerContext {
+	fn from(cmdargs: &CmdArgs<'a>) -> Self {
+		let seed = cmdargs
+			.matches
+			.value_of("seed")
+			.map(|s| s.parse::<u64>().expect("Couldn't parse seed"));
+		let iterations = cmdargs
+			.matches
+			.value_of("iter")
+			.map(|s| s.parse::<usize>().expect("Couldn't parse iterations"));
+		Self {
+			training_distribution: cmdargs
+				.matches
+				.value_of("ratio")
+				.unwrap_or("0.8")
+				.parse::<f32>()
+				.expect("Couldn't parse distribution ratio"),
+			split: cmdargs
+				.matches
+				.value_of("split")
+				.unwrap_or("random")
+				.parse::<Split>()
+				.expect("Couldn't parse split"),
+			rng_seed: seed,
+			learning_rate: cmdargs
+				.matches
+				.value_of("alpha")
+				.unwrap_or("0.1")
+				.parse::<f64>()
+				.expect("Couldn't parse alpha"),
+			schedule: cmdargs.get_schedule(),
+			theta: Vec::new(),
+			outfile: cmdargs
+				.matches
+				.value_of("outfile")
+				.unwrap_or("data/weights")
+				.to_string(),
+			model_format: cmdargs
+				.matches
+				.value_of("modelformat")
+				.unwrap_or("json")
+				.parse::<Format>()
+				.expect("Couldn't parse model format"),
+			stats_dir: cmdargs
+				.matches
+				.value_of("stats")
+				.unwrap_or("stats/")
+				.to_string(),
+			temp_diff_limit: cmdargs
+				.matches
+				.value_of("tdlimit")
+				.unwrap_or("0.001")
+				.parse::<f64>()
+				.expect("Couldn't parse temporal difference limit"),
+			iterations,
+			optimizer: cmdargs
+				.matches
+				.value_of("optimizer")
+				.unwrap_or("gd")
+				.parse::<OptimizerKind>()
+				.expect("Couldn't parse optimizer"),
+			batching: cmdargs.get_batching(),
+			solver: cmdargs
+				.matches
+				.value_of("solver")
+				.unwrap_or("gd")
+				.parse::<Solver>()
+				.expect("Couldn't parse solver"),
+			divergence: cmdargs
+				.matches
+				.value_of("divergence")
+				.unwrap_or("abort")
+				.parse::<Divergence>()
+				.expect("Couldn't parse divergence policy"),
+			divergence_tolerance: cmdargs.matches.value_of("divtolerance").map(|s| {
+				s.parse::<f64>()
+					.expect("Couldn't parse divergence tolerance")
+			}),
+			feature_scaler: cmdargs
+				.matches
+				.value_of("scale")
+				.unwrap_or("minmax")
+				.parse::<Scaler>()
+				.expect("Couldn't parse feature scaler"),
+			target_scaler: cmdargs
+				.matches
+				.value_of("scaletarget")
+				.unwrap_or("none")
+				.parse::<Scaler>()
+				.expect("Couldn't parse target scaler"),
+			cross_validation: cmdargs.get_cross_validation(),
+			regularization: cmdargs.get_regularization(),
+			loss: cmdargs
+				.matches
+				.value_of("loss")
+				.unwrap_or("squared")
+				.parse::<LossKind>()
+				.expect("Couldn't parse loss"),
+			ransac: cmdargs.get_ransac(),
+			path: cmdargs.get_path(),
+		}
+	}
+}
+
+impl<'a> From<&CmdArgs<'a>> for LoadOptions {
+	fn from(cmdargs: &CmdArgs<'a>) -> Self {
+		Self {
+			features: cmdargs.get_features(),
+			target: cmdargs.get_target(),
+			csv: CsvOptions {
+				delimiter: cmdargs.get_delimiter(),
+				has_header: !cmdargs.matches.is_present("noheader"),
+			},
+			missing: cmdargs.get_missing_policy(),
+			weight: cmdargs.matches.value_of("weight").map(Column::from),
+		}
+	}
 }
@@ -13,25 +13,98 @@
 //!
 //! FLAGS:
 //!     -h, --help       Prints help information
+//!         --no-header  Input data file has no header line, columns can be selected by index only
+//!         --refit      Trains the saved model on the full dataset instead of the train set
 //!     -V, --version    Prints version information
 //!
 //! OPTIONS:
 //!     -a, --alpha <alpha>        α - Learning rate
+//!         --batch-size <batchsize>
+//!                                Number of samples per update, implies mini-batch [default: 32]
+//!         --batching <batching>  Gradient descent batching, stochastic modes reshuffle the training set every epoch
+//!                                [default: full]  [possible values: full, sgd, mini-batch]
+//!         --cv <cv>              Cross-validates with given number of folds, or leave-one-out for loo, before the
+//!                                training
+//!         --cv-repeats <cvrepeats>
+//!                                Number of times the k-fold cross-validation is repeated with a different shuffle
+//!                                [default: 1]
+//!     -d, --delimiter <delimiter>
+//!                                Field delimiter of the input data file, single character or "tab" [default: ,]
+//!         --divergence-tolerance <divtolerance>
+//!                                Relative cost increase per epoch accepted before diverging. Defaults to 1e-9 for
+//!                                full batch gd, other setups 10 over their mean epoch cost
+//!         --features <features>...
+//!                                Comma separated feature columns for multivariate regression, header names or
+//!                                zero-based indices
 //!     -f, --file <datafile>      Input data file
-//!     -i, --iterations <iter>    Number of iterations to run, this will overwrite TD limit
+//!     -i, --iterations <iter>    Number of iterations (epochs) to run, this will overwrite TD limit
+//!         --impute <impute>      Value used by "--missing impute", mean, median or a constant [default: mean]
+//!         --l1-ratio <l1ratio>   Share of the L1 penalty in the elastic-net, 0 is ridge and 1 is lasso [default: 0.5]
+//!         --lambda <lambda>      λ - Strength of the penalty [default: 1]
+//!         --lambda-rule <lambdarule>
+//!                                Lambda picked from the path, lowest cross-validated error or the largest within one
+//!                                standard error of it [default: 1se]  [possible values: min, 1se]
+//!         --loss <loss>          Loss of the residuals minimized by the gradient descent, squared, absolute, huber,
+//!                                log-cosh or quantile. Parameters follow as name:param=value,... e.g. huber:delta=1 or
+//!                                quantile:tau=0.9 [default: squared]
+//!         --missing <missing>    Handling of rows with missing or invalid values (empty, NA, non-numeric, inf)
+//!                                [default: fail]  [possible values: fail, skip, impute]
+//!         --model-format <modelformat>
+//!                                Format of the output file, versioned JSON with metadata, the legacy two line
+//!                                text or compact binary with checksum [default: json]
+//!                                [possible values: json, legacy, binary]
+//!         --on-divergence <divergence>
+//!                                Reaction to an epoch increasing the cost, abort with an error or roll back and
+//!                                retry with halved learning rate [default: abort]  [possible values: abort, backoff]
+//!         --optimizer <optimizer>
+//!                                Gradient descent update rule, gd, momentum, nesterov, adagrad, rmsprop or adam.
+//!                                Hyperparameters follow as name:param=value,... e.g.
+//!                                adam:beta1=0.9,beta2=0.999,epsilon=1e-8 [default: gd]
 //!     -o, --out <outfile>        Path to output file (model)
+//!         --path <path>          Fits the penalty along given number of log-spaced lambdas with warm starts and picks
+//!                                lambda by cross-validation, folds of --cv or 5
+//!         --path-ratio <pathratio>
+//!                                Smallest lambda of the path as a fraction of the largest one, which zeroes all
+//!                                coefficients [default: 0.001]
+//!         --penalty <penalty>    Regularization of the scaled coefficients, never of the intercept. Lasso and
+//!                                elastic-net need the gd or cd solver [default: none]
+//!                                [possible values: none, ridge, lasso, elastic-net]
+//!         --ransac-threshold <ransacthreshold>
+//!                                Largest residual of a RANSAC inlier, in target units [default: median absolute
+//!                                deviation of the target]
+//!         --ransac-trials <ransactrials>
+//!                                Number of random subsets fitted by RANSAC [default: 100]
 //!     -r, --ratio <ratio>        Distribution between test and train set ratio
+//!         --scale <scale>        Feature scaling for the gradient descent and the penalties, robust uses median and
+//!                                interquartile range [default: minmax]  [possible values: none, minmax, zscore, robust]
+//!         --scale-target <scaletarget>
+//!                                Target scaling for the gradient descent, TD limit then applies to the scaled
+//!                                problem [default: none]  [possible values: none, minmax, zscore, robust]
+//!         --schedule <schedule>
+//!                                Learning rate schedule, constant, step, exponential, inverse-time or cosine.
+//!                                Parameters follow as name:param=value,... e.g. step:factor=0.5,every=100
+//!                                [default: constant]
 //!     -s, --seed <seed>          Randomness seed for data splitting to train & test sets
+//!         --solver <solver>      Gradient descent, exact least squares solution by normal equation or QR
+//!                                decomposition, coordinate descent, or the outlier resistant RANSAC and Theil-Sen
+//!                                (single feature) [default: gd]  [possible values: gd, normal-equation, qr, cd, ransac,
+//!                                theil-sen]
+//!         --split <split>        Train and test split, random, stratified:bins=<n> on the target or chronological with
+//!                                the last rows as test set [default: random]
 //!         --stats <stats>        Path to a directory where plots and statistics should be saved
 //!     -t, --tdlimit <tdlimit>    Temporal difference limit (amout of change per iteration). How accurate local minima is.
+//!         --warmup <warmup>      Number of epochs to linearly increase the learning rate to alpha before the
+//!                                schedule applies [default: 0]
+//!         --weight <weight>      Column of the sample weights, header name or zero-based index. Negative weights are
+//!                                rejected, rows of zero weight ignored [default: all weigh 1]
+//!         --x <x>                Feature column, header name or zero-based index [default: 0]
+//!         --y <y>                Target column, header name or zero-based index [default: 1]
 //!
 //! ```
-mod trainer;
-use trainer::{Trainer, TrainerContext};
+use ft_linear_regression::dataset::LoadOptions;
+use ft_linear_regression::{Dataset, Result, TrainError, Trainer, TrainerContext};
 mod arguments;
 use arguments::CmdArgs;
-mod result;
-use result::{Result, TrainError};
 
 /// Main
 fn main() -> Result<()> {
@@ -40,11 +113,19 @@ fn main() -> Result<()> {
 	let ctx = TrainerContext::from(&cmdargs);
 	let filename = cmdargs.get_infile();
 	println!("Input data location {}", filename);
-	let mut trainer = Trainer::load(filename, Some(ctx));
-	trainer.train();
+	let (dataset, report) = Dataset::load_with_report(filename, &LoadOptions::from(&cmdargs))?;
+	println!("{}", report);
+	let mut trainer = Trainer::from_dataset(dataset, Some(ctx));
+	if trainer.context().path.is_some() {
+		trainer.regularization_path()?;
+	}
+	if trainer.context().cross_validation.is_some() {
+		trainer.cross_validate()?;
+	}
+	trainer.train()?;
 	trainer.test_accuracy();
 	trainer.save_output(Option::None)?;
-	match trainer.plot_result() {
+	match trainer.plot_result().and_then(|_| trainer.plot_path()) {
 		Ok(_) => {}
 		Err(e) => return Err(TrainError::Custom(format!("Plotter Error: {:?}", e))),
 	}
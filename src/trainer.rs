@@ -0,0 +1,1240 @@
+//! # ft_linear_regression training module
+//!
+//! Loads a [`Dataset`], splits it to train and test sets, fits the [`LinearModel`]
+//! using gradient descent with an [`Optimizer`](crate::optimizer::Optimizer) or one of
+//! closed-form [`Solver`]s and reports on the result.
+use rand::prelude::*;
+
+use plotters::prelude::*;
+
+use crate::dataset::{Dataset, Sample, Split};
+use crate::domain::Domain;
+use crate::inference::Inference;
+use crate::loss::{self, LossKind};
+use crate::metrics::{self, Metrics};
+use crate::model::LinearModel;
+use crate::model_io::{self, Format, Metadata, SavedModel};
+use crate::optimizer::{Batching, Divergence, OptimizerKind};
+use crate::path::{self, PathSettings, RegularizationPath};
+use crate::regularization::{Penalty, Regularization};
+use crate::result::{Result, TrainError};
+use crate::robust::{self, Ransac, RansacFit};
+use crate::scaler::{SampleScaling, Scaler};
+use crate::schedule::Schedule;
+use crate::solver::{self, Solver};
+use crate::validation::{CrossValidation, CvReport, Folds};
+
+/// Number of learning rate halvings before the training gives up
+const MAX_BACKOFFS: usize = 32;
+/// Coordinate descent sweeps if `TrainerContext::iterations` is not set
+const MAX_SWEEPS: usize = 100_000;
+
+/// Main training struct
+#[derive(Debug)]
+pub struct Trainer {
+	/// Context struct
+	ctx: TrainerContext,
+	/// Loaded dataset
+	dataset: Dataset,
+	/// Test set
+	test_set: Dataset,
+	/// Training set
+	train_set: Dataset,
+	/// Quality measures on the training set
+	train_metrics: Option<Metrics>,
+	/// Quality measures on the test set
+	test_metrics: Option<Metrics>,
+	/// Learning rate of the last gradient descent epoch
+	final_learning_rate: Option<f64>,
+	/// Scaling of the training set used by the gradient descent
+	scaling: Option<SampleScaling>,
+	/// Statistics for the intervals of the fitted theta
+	inference: Option<Inference>,
+	/// Range of the training features
+	domain: Option<Domain>,
+	/// Result of the cross-validation
+	cv_report: Option<CvReport>,
+	/// Regularization path with the cross-validated error of every lambda
+	path: Option<RegularizationPath>,
+	/// RANSAC fit with the inliers of the training set
+	ransac: Option<RansacFit>,
+}
+
+impl Trainer {
+	/// Function that loads the `Trainer` struct from given file.
+	///
+	/// See [`Dataset::load`] for assumptions about the file.
+	///
+	pub fn load(filename: &str, ctx: Option<TrainerContext>) -> Result<Self> {
+		Ok(Self::from_dataset(Dataset::load(filename)?, ctx))
+	}
+
+	/// Creates the `Trainer` from already loaded dataset.
+	pub fn from_dataset(dataset: Dataset, ctx: Option<TrainerContext>) -> Self {
+		Self {
+			ctx: ctx.unwrap_or_default(),
+			test_set: dataset.empty_like(),
+			train_set: dataset.empty_like(),
+			dataset,
+			train_metrics: Option::None,
+			test_metrics: Option::None,
+			final_learning_rate: Option::None,
+			scaling: Option::None,
+			inference: Option::None,
+			domain: Option::None,
+			cv_report: Option::None,
+			path: Option::None,
+			ransac: Option::None,
+		}
+	}
+
+	/// Splits the input dataset into train and test sets.
+	///
+	/// The split is done by `TrainerContext::split`, in preset ratio (`TrainerContext::training_distribution`).
+	/// Seed can be saved for reproducibility.
+	///
+	fn split_dataset(&mut self) {
+		let seed = self.ctx.get_seed();
+		let (train_set, test_set) =
+			self.dataset
+				.split_with(self.ctx.split, self.ctx.training_distribution, seed);
+		self.train_set = train_set;
+		self.test_set = test_set;
+	}
+
+	/// Fits theta on the training set with the solver of `TrainerContext::solver`.
+	///
+	/// With [`CrossValidation::refit`] the whole dataset is used for training.
+	pub fn train(&mut self) -> Result<()> {
+		match self.ctx.cross_validation {
+			Some(cv) if cv.refit => {
+				self.train_set = self.dataset.clone();
+				self.test_set = self.dataset.empty_like();
+			}
+			_ => self.split_dataset(),
+		}
+		self.fit()
+	}
+
+	/// Fits a model per fold of `TrainerContext::cross_validation` and prints the report
+	///
+	/// Theta of the trainer is left as it was, use [`Trainer::train`] for the saved model.
+	pub fn cross_validate(&mut self) -> Result<CvReport> {
+		let cv = self.cross_validation()?;
+		let seed = self.ctx.get_seed();
+		let initial_theta = self.ctx.theta.clone();
+		let mut folds = Vec::new();
+		for (train_set, test_set) in cv.splits(&self.dataset, seed) {
+			self.train_set = train_set;
+			self.test_set = test_set;
+			self.ctx.theta = initial_theta.clone();
+			self.fit()?;
+			folds.extend(Metrics::evaluate(&self.model(), &self.test_set));
+		}
+		self.ctx.theta = initial_theta;
+		let report = CvReport { folds };
+		println!("{}", report);
+		self.cv_report = Some(report.clone());
+		Ok(report)
+	}
+
+	/// Fits the penalty along the lambdas of `TrainerContext::path` and sets the one picked by cross-validation
+	///
+	/// The path is fitted by coordinate descent on the whole dataset and on the folds
+	/// of `TrainerContext::cross_validation`, 5-fold by default. Use [`Trainer::train`]
+	/// for the saved model.
+	pub fn regularization_path(&mut self) -> Result<RegularizationPath> {
+		let settings = self.ctx.path.unwrap_or_default();
+		settings.validate().map_err(TrainError::Custom)?;
+		let regularization = self.ctx.regularization;
+		regularization.validate().map_err(TrainError::Custom)?;
+		if regularization.penalty == Penalty::None {
+			return Err(TrainError::Custom(String::from(
+				"Regularization path needs a penalty",
+			)));
+		}
+		if self.ctx.loss != LossKind::Squared {
+			return Err(TrainError::Custom(format!(
+				"Regularization path needs the squared loss, not {}",
+				self.ctx.loss
+			)));
+		}
+		let cv = self.cross_validation()?;
+		let seed = self.ctx.get_seed();
+		let (tolerance, max_sweeps) = (
+			self.ctx.temp_diff_limit,
+			self.ctx.iterations.unwrap_or(MAX_SWEEPS),
+		);
+		let (feature_scaler, target_scaler) = (self.ctx.feature_scaler, self.ctx.target_scaler);
+		let scaling =
+			|samples: &[Sample]| SampleScaling::fit(samples, feature_scaler, target_scaler);
+
+		let full = scaling(&self.dataset.samples);
+		let scaled: Vec<Sample> = self
+			.dataset
+			.samples
+			.iter()
+			.map(|sample| full.apply(sample))
+			.collect();
+		let lambdas = settings.lambdas(&scaled, &regularization);
+		let coefficients = path::fit_path(
+			&self.dataset.samples,
+			&full,
+			&regularization,
+			&lambdas,
+			tolerance,
+			max_sweeps,
+		);
+		let mut fold_mse = vec![Vec::new(); lambdas.len()];
+		for (train_set, test_set) in cv.splits(&self.dataset, seed) {
+			let thetas = path::fit_path(
+				&train_set.samples,
+				&scaling(&train_set.samples),
+				&regularization,
+				&lambdas,
+				tolerance,
+				max_sweeps,
+			);
+			for (errors, theta) in fold_mse.iter_mut().zip(thetas.iter()) {
+				errors.push(path::mse(theta, &test_set.samples));
+			}
+		}
+		let path = RegularizationPath::select(lambdas, coefficients, &fold_mse, settings.rule);
+		println!("{}", path);
+		self.ctx.regularization.lambda = path.chosen();
+		self.path = Some(path.clone());
+		Ok(path)
+	}
+
+	/// Effective `TrainerContext::cross_validation`, checked against the dataset length
+	fn cross_validation(&self) -> Result<CrossValidation> {
+		let cv = self.ctx.cross_validation.unwrap_or_default();
+		if cv.repeats == 0 {
+			return Err(TrainError::Custom(String::from(
+				"Cross-validation needs at least one repeat",
+			)));
+		}
+		if let Folds::K(k) = cv.folds {
+			if k > self.dataset.len() {
+				return Err(TrainError::Custom(format!(
+					"Can't make {} folds of {} samples",
+					k,
+					self.dataset.len()
+				)));
+			}
+		}
+		Ok(cv)
+	}
+
+	/// Fits theta on the current training set
+	fn fit(&mut self) -> Result<()> {
+		let regularization = self.ctx.regularization;
+		regularization.validate().map_err(TrainError::Custom)?;
+		if !regularization.is_none() && !self.ctx.solver.supports_penalty() {
+			return Err(TrainError::Custom(format!(
+				"{} solver doesn't support penalties",
+				self.ctx.solver
+			)));
+		}
+		if regularization.l1() > 0.0 && !self.ctx.solver.supports_l1() {
+			return Err(TrainError::Custom(format!(
+				"{} penalty needs the gd or cd solver",
+				regularization.penalty
+			)));
+		}
+		if self.ctx.loss != LossKind::Squared && self.ctx.solver != Solver::GradientDescent {
+			return Err(TrainError::Custom(format!(
+				"{} loss needs the gd solver",
+				self.ctx.loss
+			)));
+		}
+		self.ransac = Option::None;
+		match self.ctx.solver {
+			Solver::GradientDescent => self.gradient_descent()?,
+			Solver::NormalEquation | Solver::Qr if !regularization.is_none() => {
+				self.scaled_solve()?
+			}
+			Solver::CoordinateDescent => self.scaled_solve()?,
+			Solver::NormalEquation => {
+				self.ctx.theta =
+					solver::normal_equation(&self.train_set.samples, self.dataset.feature_count())?;
+				println!("Training finished, solved normal equation.");
+			}
+			Solver::Qr => {
+				self.ctx.theta = solver::qr(&self.train_set.samples, self.dataset.feature_count())?;
+				println!("Training finished, solved least squares by QR decomposition.");
+			}
+			Solver::Ransac => {
+				let seed = self.ctx.get_seed();
+				let fit = robust::ransac(&self.train_set.samples, &self.ctx.ransac, seed)?;
+				println!(
+					"Training finished, RANSAC kept {} of {} samples as inliers (threshold {}, {} trials).",
+					fit.inlier_count(),
+					fit.inliers.len(),
+					fit.threshold,
+					self.ctx.ransac.trials
+				);
+				self.ctx.theta = fit.theta.clone();
+				self.ransac = Some(fit);
+			}
+			Solver::TheilSen => {
+				let seed = self.ctx.get_seed();
+				self.ctx.theta = robust::theil_sen(&self.train_set.samples, seed)?;
+				println!("Training finished, Theil-Sen median of pairwise slopes.");
+			}
+		}
+		self.domain = Some(Domain::fit(&self.train_set.samples));
+		// The intervals assume an unbiased least squares fit
+		if !regularization.is_none()
+			|| self.ctx.loss != LossKind::Squared
+			|| self.ctx.solver == Solver::TheilSen
+		{
+			self.inference = Option::None;
+			println!(
+				"Prediction intervals are only available for unregularized least squares fits"
+			);
+			return Ok(());
+		}
+		self.inference = match &self.ransac {
+			// Least squares of the inliers
+			Some(fit) => {
+				let inliers: Vec<Sample> = self
+					.train_set
+					.samples
+					.iter()
+					.zip(fit.inliers.iter())
+					.filter(|(_, inlier)| **inlier)
+					.map(|(sample, _)| sample.clone())
+					.collect();
+				Inference::fit(&inliers, &self.ctx.theta)
+			}
+			None => Inference::fit(&self.train_set.samples, &self.ctx.theta),
+		};
+		match &self.inference {
+			Some(inference) => println!(
+				"Residual standard error {:.3} on {} degrees of freedom",
+				inference.residual_se,
+				inference.degrees_of_freedom()
+			),
+			None => println!("Not enough samples for prediction intervals"),
+		}
+		Ok(())
+	}
+
+	/// Solver fit of the penalized cost on the features and target scaled as for the gradient descent
+	///
+	/// The ridge penalty is solved exactly by the closed-form solvers, the coordinate
+	/// descent runs until no coefficient changes by more than `TrainerContext::temp_diff_limit`
+	/// or for `TrainerContext::iterations` sweeps.
+	fn scaled_solve(&mut self) -> Result<()> {
+		let scaling = SampleScaling::fit(
+			&self.train_set.samples,
+			self.ctx.feature_scaler,
+			self.ctx.target_scaler,
+		);
+		let scaled: Vec<Sample> = self
+			.train_set
+			.samples
+			.iter()
+			.map(|sample| scaling.apply(sample))
+			.collect();
+		let regularization = self.ctx.regularization;
+		let feature_count = self.dataset.feature_count();
+		let theta = match self.ctx.solver {
+			Solver::NormalEquation => {
+				let theta =
+					solver::ridge_normal_equation(&scaled, feature_count, regularization.l2())?;
+				println!("Training finished, solved ridge normal equation.");
+				theta
+			}
+			Solver::Qr => {
+				let theta = solver::ridge_qr(&scaled, feature_count, regularization.l2())?;
+				println!("Training finished, solved ridge least squares by QR decomposition.");
+				theta
+			}
+			_ => {
+				if scaled.is_empty() {
+					return Err(TrainError::Custom(String::from(
+						"Coordinate descent needs at least one training sample",
+					)));
+				}
+				let theta_len = feature_count + 1;
+				if self.ctx.theta.len() != theta_len {
+					self.ctx.theta = vec![0.0; theta_len];
+				}
+				let (theta, sweeps) = solver::coordinate_descent(
+					&scaled,
+					&regularization,
+					&scaling.theta_from_raw(&self.ctx.theta),
+					self.ctx.temp_diff_limit,
+					self.ctx.iterations.unwrap_or(MAX_SWEEPS),
+				);
+				println!(
+					"Training finished after {} coordinate descent sweeps ({} penalty).",
+					sweeps, regularization.penalty
+				);
+				theta
+			}
+		};
+		self.ctx.theta = scaling.theta_to_raw(&theta);
+		self.scaling = Some(scaling);
+		Ok(())
+	}
+
+	/// Gradient descent on features and target scaled by `TrainerContext::feature_scaler`
+	/// and `TrainerContext::target_scaler`
+	///
+	/// Runs in epochs, each being a pass over the shuffled training set in batches
+	/// of `TrainerContext::batching`. Epochs that increase the cost are handled
+	/// according to `TrainerContext::divergence`. The cost is the mean `TrainerContext::loss`,
+	/// the L2 penalty is part of the gradient, the L1 penalty is applied by soft-thresholding
+	/// after every step (proximal gradient).
+	fn gradient_descent(&mut self) -> Result<()> {
+		if self.train_set.is_empty() {
+			return Err(TrainError::Custom(String::from(
+				"Gradient descent needs at least one training sample",
+			)));
+		}
+		let scaling = SampleScaling::fit(
+			&self.train_set.samples,
+			self.ctx.feature_scaler,
+			self.ctx.target_scaler,
+		);
+		let mut normalized: Vec<Sample> = self
+			.train_set
+			.samples
+			.iter()
+			.map(|sample| scaling.apply(sample))
+			.collect();
+		let mut optimizer = self.ctx.optimizer.build();
+		let theta_len = self.dataset.feature_count() + 1;
+		if self.ctx.theta.len() != theta_len {
+			self.ctx.theta = vec![0.0; theta_len];
+		}
+		// Theta of the scaled data, initial theta is given for raw data
+		let mut theta = scaling.theta_from_raw(&self.ctx.theta);
+
+		let batch_size = self.ctx.batching.batch_size(normalized.len());
+		let mut r = StdRng::seed_from_u64(self.ctx.get_seed());
+
+		let mut epoch: usize = 0;
+		let mut updates: usize = 0;
+		let mut learning_rate = self.ctx.learning_rate;
+		// Multiplier of the scheduled learning rate, halved on every backoff
+		let mut backoff_scale = 1.0;
+		let mut backoffs: usize = 0;
+		let regularization = self.ctx.regularization;
+		let loss = self.ctx.loss.build();
+		let mut cost = loss::cost(&*loss, &theta, &normalized) + regularization.cost(&theta);
+		let tolerance = self.ctx.divergence_tolerance();
+		// Epochs compare to the previous cost if it should decrease monotonically,
+		// to the mean of the accepted epoch costs otherwise
+		let monotone = self.ctx.is_monotone();
+		let (mut cost_sum, mut cost_count) = (cost, 1.0);
+
+		// Temporal difference init
+		let mut temp_diff: Vec<f64> = vec![1.0; theta_len];
+
+		// Main loop
+		// Runs while epochs limit or precision is not reached
+		while !self.ctx.is_done(epoch, &temp_diff) {
+			if self.ctx.batching.shuffles() {
+				normalized.shuffle(&mut r);
+			}
+			let previous_theta = theta.clone();
+			// Temporal difference of the epoch is the total change of theta
+			let mut epoch_diff = vec![0.0; theta_len];
+			learning_rate = self.ctx.schedule.alpha(self.ctx.learning_rate, epoch) * backoff_scale;
+			for batch in normalized.chunks(batch_size) {
+				let step_diff = optimizer.step(
+					&mut theta,
+					&|theta| {
+						let mut gradient = loss::gradient(&*loss, theta, batch);
+						regularization.add_gradient(theta, &mut gradient);
+						gradient
+					},
+					learning_rate,
+				);
+				let shrink_diff = regularization.shrink(&mut theta, learning_rate);
+				for ((d, s), p) in epoch_diff
+					.iter_mut()
+					.zip(step_diff.iter())
+					.zip(shrink_diff.iter())
+				{
+					*d += s + p;
+				}
+				updates += 1;
+			}
+			// Cost has to decrease, otherwise the learning rate is too large
+			let epoch_cost = loss::cost(&*loss, &theta, &normalized) + regularization.cost(&theta);
+			let reference = match monotone {
+				true => cost,
+				false => cost_sum / cost_count,
+			};
+			if !epoch_cost.is_finite() || epoch_cost > reference * (1.0 + tolerance) {
+				let diverged = TrainError::Diverged {
+					epoch,
+					learning_rate,
+					cost: epoch_cost,
+				};
+				if self.ctx.divergence == Divergence::Abort || backoffs == MAX_BACKOFFS {
+					return Err(diverged);
+				}
+				println!("{}, retrying with halved learning rate", diverged);
+				theta = previous_theta;
+				// Optimizer state was built up by the rejected steps
+				optimizer = self.ctx.optimizer.build();
+				backoff_scale *= 0.5;
+				backoffs += 1;
+				continue;
+			}
+			cost = epoch_cost;
+			cost_sum += epoch_cost;
+			cost_count += 1.0;
+			temp_diff = epoch_diff;
+			// increase epoch count
+			epoch += 1;
+		}
+
+		println!(
+			"Training finished after {} epochs ({} updates, {} batching, {} optimizer).\nFinal learning rate {} (schedule {}, {} backoffs)\nTemporal difference {:?}",
+			epoch, updates, self.ctx.batching, self.ctx.optimizer, learning_rate, self.ctx.schedule, backoffs, temp_diff
+		);
+		self.final_learning_rate = Some(learning_rate);
+
+		// Scale theta back
+		self.ctx.theta = scaling.theta_to_raw(&theta);
+		self.scaling = Some(scaling);
+		Ok(())
+	}
+
+	/// Evaluates the trained model on the train and test sets and prints the [`Metrics`] table
+	pub fn test_accuracy(&mut self) {
+		let model = self.model();
+		self.train_metrics = Metrics::evaluate(&model, &self.train_set);
+		self.test_metrics = Metrics::evaluate(&model, &self.test_set);
+		if self.test_set.is_empty() {
+			println!("No test set available");
+		}
+		println!(
+			"{}",
+			metrics::table(&[
+				("train", self.train_metrics.as_ref()),
+				("test", self.test_metrics.as_ref())
+			])
+		);
+	}
+
+	/// Trained model getter
+	pub fn model(&self) -> LinearModel {
+		LinearModel::new(
+			self.dataset.feature_labels.clone(),
+			self.dataset.target_label.clone(),
+			self.ctx.theta.clone(),
+		)
+	}
+
+	/// Context getter
+	pub fn context(&self) -> &TrainerContext {
+		&self.ctx
+	}
+
+	/// Quality measures on the training set, available after [`Trainer::test_accuracy`]
+	pub fn train_metrics(&self) -> Option<&Metrics> {
+		self.train_metrics.as_ref()
+	}
+
+	/// Quality measures on the test set, available after [`Trainer::test_accuracy`]
+	///
+	/// `Option::None` if the test set is empty.
+	pub fn test_metrics(&self) -> Option<&Metrics> {
+		self.test_metrics.as_ref()
+	}
+
+	fn get_bounding_box(&self, offset: f64) -> (f64, f64, f64, f64) {
+		let mut x_min = f64::MAX;
+		let mut x_max = f64::MIN;
+		let mut y_min = f64::MAX;
+		let mut y_max = f64::MIN;
+		// Find min and max values
+		for sample in self.dataset.samples.iter() {
+			x_min = x_min.min(sample.features[0]);
+			x_max = x_max.max(sample.features[0]);
+			y_min = y_min.min(sample.target);
+			y_max = y_max.max(sample.target);
+		}
+		// calculate x and y offsets
+		let x_off = (x_max - x_min) * offset;
+		let y_off = (y_max - y_min) * offset;
+
+		(x_min - x_off, y_min - y_off, x_max + x_off, y_max + y_off)
+	}
+	/// Training settings, data and results stored with the model
+	pub fn metadata(&self) -> Metadata {
+		let ctx = &self.ctx;
+		let mut hyperparameters = vec![
+			("solver", ctx.solver.to_string()),
+			(
+				"training_distribution",
+				ctx.training_distribution.to_string(),
+			),
+			("split", ctx.split.to_string()),
+		];
+		if let Some(label) = &self.dataset.weight_label {
+			hyperparameters.push(("weight", label.clone()));
+		}
+		if let Some(cv) = ctx.cross_validation {
+			hyperparameters.extend([
+				("cv_folds", cv.folds.to_string()),
+				("cv_repeats", cv.repeats.to_string()),
+				("refit", cv.refit.to_string()),
+			]);
+		}
+		if !ctx.regularization.is_none() {
+			hyperparameters.extend([
+				("penalty", ctx.regularization.penalty.to_string()),
+				("lambda", ctx.regularization.lambda.to_string()),
+			]);
+			if ctx.regularization.penalty == Penalty::ElasticNet {
+				hyperparameters.push(("l1_ratio", ctx.regularization.l1_ratio.to_string()));
+			}
+			if let Some(path) = &self.path {
+				hyperparameters.push(("lambda_rule", path.rule.to_string()));
+			}
+			if ctx.solver != Solver::GradientDescent {
+				hyperparameters.extend([
+					("feature_scaler", ctx.feature_scaler.to_string()),
+					("target_scaler", ctx.target_scaler.to_string()),
+				]);
+			}
+		}
+		if let Some(fit) = &self.ransac {
+			hyperparameters.extend([
+				("ransac_threshold", fit.threshold.to_string()),
+				("ransac_trials", ctx.ransac.trials.to_string()),
+			]);
+		}
+		if ctx.solver == Solver::CoordinateDescent {
+			hyperparameters.push(("temp_diff_limit", ctx.temp_diff_limit.to_string()));
+			if let Some(iterations) = ctx.iterations {
+				hyperparameters.push(("iterations", iterations.to_string()));
+			}
+		}
+		if ctx.solver == Solver::GradientDescent {
+			hyperparameters.extend([
+				("loss", ctx.loss.to_string()),
+				("learning_rate", ctx.learning_rate.to_string()),
+				("schedule", ctx.schedule.decay.to_string()),
+				("warmup", ctx.schedule.warmup.to_string()),
+				("optimizer", ctx.optimizer.to_string()),
+				("batching", ctx.batching.to_string()),
+				("temp_diff_limit", ctx.temp_diff_limit.to_string()),
+				("feature_scaler", ctx.feature_scaler.to_string()),
+				("target_scaler", ctx.target_scaler.to_string()),
+				("divergence", ctx.divergence.to_string()),
+			]);
+			if let Some(iterations) = ctx.iterations {
+				hyperparameters.push(("iterations", iterations.to_string()));
+			}
+		}
+		Metadata {
+			scaling: self.scaling.clone(),
+			inference: self.inference.clone(),
+			domain: self.domain.clone(),
+			path: self.path.clone(),
+			hyperparameters: hyperparameters
+				.into_iter()
+				.map(|(k, v)| (k.to_string(), v))
+				.collect(),
+			seed: ctx.rng_seed,
+			dataset_hash: Some(self.dataset.fingerprint()),
+			metrics: [
+				("train_", &self.train_metrics),
+				("test_", &self.test_metrics),
+			]
+			.iter()
+			.filter_map(|(prefix, m)| m.as_ref().map(|m| m.named(prefix)))
+			.flatten()
+			.chain(self.cv_report.iter().flat_map(|report| report.named()))
+			.collect(),
+			timestamp: Some(model_io::timestamp()),
+		}
+	}
+
+	/// Saves the model in `TrainerContext::model_format` into a file.
+	///
+	/// If the path_overwrite is `Option::None`, then `TrainerContext::outfile` is used.
+	pub fn save_output(&self, path_overwrite: Option<&str>) -> std::io::Result<()> {
+		let saved = SavedModel {
+			model: self.model(),
+			metadata: self.metadata(),
+		};
+		model_io::save_as(
+			&saved,
+			path_overwrite.unwrap_or(&self.ctx.outfile),
+			self.ctx.model_format,
+		)
+	}
+
+	/// Plots the dataset and the regression line.
+	///
+	/// Only single feature models can be plotted, otherwise this is a no-op.
+	pub fn plot_result(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+		if self.dataset.feature_count() != 1 {
+			println!("Plotting is only available for single feature models");
+			return Ok(());
+		}
+		let path = format!("{}/result.png", self.ctx.stats_dir);
+
+		let root = BitMapBackend::new(&path, (1920, 1080)).into_drawing_area();
+		root.fill(&WHITE)?;
+		let root = root.titled("ft_linear_regression", ("sans-serif", 60))?;
+
+		let bbox = self.get_bounding_box(0.1);
+		let mut scatter_ctx = ChartBuilder::on(&root)
+			.x_label_area_size(80)
+			.y_label_area_size(120)
+			.margin(5)
+			.caption(self.get_summary(), ("sans-serif", 30))
+			.build_cartesian_2d(bbox.0..bbox.2, bbox.1..bbox.3)?;
+		scatter_ctx
+			.configure_mesh()
+			.light_line_style(WHITE)
+			.x_desc(&self.dataset.feature_labels[0])
+			.y_desc(&self.dataset.target_label)
+			.axis_desc_style(("sans-serif", 40))
+			.label_style(("sans-serif", 22))
+			.draw()?;
+		scatter_ctx.draw_series(
+			self.dataset
+				.samples
+				.iter()
+				.map(|s| Circle::new((s.features[0], s.target), 5, GREEN.filled())),
+		)?;
+		if let Some(inliers) = self.inliers() {
+			scatter_ctx
+				.draw_series(
+					self.train_set
+						.samples
+						.iter()
+						.zip(inliers.iter())
+						.filter(|(_, inlier)| !**inlier)
+						.map(|(s, _)| Circle::new((s.features[0], s.target), 5, RED.filled())),
+				)?
+				.label("RANSAC outliers")
+				.legend(|(x, y)| Circle::new((x, y), 5, RED.filled()));
+			scatter_ctx
+				.configure_series_labels()
+				.background_style(WHITE.mix(0.8))
+				.border_style(BLACK)
+				.label_font(("sans-serif", 22))
+				.draw()?;
+		}
+		let x_axis = [bbox.0, bbox.2];
+		let color = Palette99::pick(0).stroke_width(3);
+		let model = self.model();
+		scatter_ctx.draw_series(LineSeries::new(
+			x_axis.iter().map(|x| (*x, model.estimate(&[*x]))),
+			color,
+		))?;
+		// To avoid the IO failure being ignored silently, we manually call the present function
+		root.present()?;
+		println!("Result has been saved to {}", path);
+		Ok(())
+	}
+
+	/// Plots the coefficients of the scaled features along the regularization path.
+	///
+	/// No-op unless [`Trainer::regularization_path`] was run.
+	pub fn plot_path(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+		let path = match &self.path {
+			Some(path) if !path.lambdas.is_empty() => path,
+			_ => return Ok(()),
+		};
+		let file = format!("{}/path.png", self.ctx.stats_dir);
+		let scaling = SampleScaling::fit(
+			&self.dataset.samples,
+			self.ctx.feature_scaler,
+			self.ctx.target_scaler,
+		);
+		let scaled: Vec<Vec<f64>> = path
+			.coefficients
+			.iter()
+			.map(|theta| scaling.theta_from_raw(theta))
+			.collect();
+		let log_lambdas: Vec<f64> = path.lambdas.iter().map(|l| l.log10()).collect();
+		let (x_min, x_max) = (log_lambdas[log_lambdas.len() - 1], log_lambdas[0]);
+		let (mut y_min, mut y_max) = (0.0_f64, 0.0_f64);
+		for theta in scaled.iter() {
+			for t in theta[1..].iter() {
+				y_min = y_min.min(*t);
+				y_max = y_max.max(*t);
+			}
+		}
+		let y_off = ((y_max - y_min) * 0.1).max(f64::EPSILON);
+
+		let root = BitMapBackend::new(&file, (1920, 1080)).into_drawing_area();
+		root.fill(&WHITE)?;
+		let root = root.titled("Regularization path", ("sans-serif", 60))?;
+		let mut chart = ChartBuilder::on(&root)
+			.x_label_area_size(80)
+			.y_label_area_size(120)
+			.margin(5)
+			.caption(
+				format!(
+					"{} penalty; lambda_min {:.3e}; lambda_1se {:.3e}; chosen {}",
+					self.ctx.regularization.penalty, path.lambda_min, path.lambda_1se, path.rule
+				),
+				("sans-serif", 30),
+			)
+			.build_cartesian_2d(
+				x_min..x_max.max(x_min + f64::EPSILON),
+				(y_min - y_off)..(y_max + y_off),
+			)?;
+		chart
+			.configure_mesh()
+			.light_line_style(WHITE)
+			.x_desc("log10(lambda)")
+			.y_desc("coefficient of the scaled feature")
+			.axis_desc_style(("sans-serif", 40))
+			.label_style(("sans-serif", 22))
+			.draw()?;
+		for (j, label) in self.dataset.feature_labels.iter().enumerate() {
+			let color = Palette99::pick(j);
+			chart
+				.draw_series(LineSeries::new(
+					log_lambdas
+						.iter()
+						.zip(scaled.iter())
+						.map(|(x, theta)| (*x, theta[j + 1])),
+					color.stroke_width(3),
+				))?
+				.label(label)
+				.legend(move |(x, y)| {
+					PathElement::new(
+						vec![(x, y), (x + 20, y)],
+						Palette99::pick(j).stroke_width(3),
+					)
+				});
+		}
+		for lambda in [path.lambda_min, path.lambda_1se] {
+			let x = lambda.log10();
+			chart.draw_series(LineSeries::new(
+				[(x, y_min - y_off), (x, y_max + y_off)],
+				BLACK.stroke_width(1),
+			))?;
+		}
+		chart
+			.configure_series_labels()
+			.background_style(WHITE.mix(0.8))
+			.border_style(BLACK)
+			.label_font(("sans-serif", 22))
+			.draw()?;
+		// To avoid the IO failure being ignored silently, we manually call the present function
+		root.present()?;
+		println!("Regularization path has been saved to {}", file);
+		Ok(())
+	}
+
+	/// Learning rate of the last gradient descent epoch, `Option::None` for closed-form solvers
+	pub fn final_learning_rate(&self) -> Option<f64> {
+		self.final_learning_rate
+	}
+
+	/// Scaling fitted on the training set, `Option::None` for unregularized closed-form solvers
+	///
+	/// Theta of the [`Trainer::model`] is already folded back for raw values.
+	pub fn scaling(&self) -> Option<&SampleScaling> {
+		self.scaling.as_ref()
+	}
+
+	/// Statistics for the confidence and prediction intervals, available after [`Trainer::train`]
+	///
+	/// `Option::None` if there are no more training samples than coefficients
+	/// or the features are collinear.
+	pub fn inference(&self) -> Option<&Inference> {
+		self.inference.as_ref()
+	}
+
+	/// Result of [`Trainer::cross_validate`]
+	pub fn cv_report(&self) -> Option<&CvReport> {
+		self.cv_report.as_ref()
+	}
+
+	/// Range of the training features, available after [`Trainer::train`]
+	pub fn domain(&self) -> Option<&Domain> {
+		self.domain.as_ref()
+	}
+
+	/// Result of [`Trainer::regularization_path`]
+	pub fn path(&self) -> Option<&RegularizationPath> {
+		self.path.as_ref()
+	}
+
+	/// Training set of the last fit
+	pub fn train_set(&self) -> &Dataset {
+		&self.train_set
+	}
+
+	/// RANSAC inlier flag per sample of [`Trainer::train_set`], `Option::None` for the other solvers
+	pub fn inliers(&self) -> Option<&[bool]> {
+		self.ransac.as_ref().map(|fit| fit.inliers.as_slice())
+	}
+
+	fn get_summary(&self) -> String {
+		let mut summary = format!(
+			"seed {}; distribution ratio {:.2}; test R² {:.3}; test RMSE {:.3}",
+			self.ctx.rng_seed.unwrap_or(0),
+			self.ctx.training_distribution,
+			self.test_metrics.map_or(f64::NAN, |m| m.r2),
+			self.test_metrics.map_or(f64::NAN, |m| m.rmse)
+		);
+		if let Some(alpha) = self.final_learning_rate {
+			summary += &format!("; final alpha {:.3e}", alpha);
+		}
+		summary
+	}
+}
+
+/// Context struct for trainer.
+///
+/// To-Do:
+///     - change paths to `&str` to avoid pointless allocations
+///     - paths could be options, if none -> stdout
+#[derive(Debug)]
+pub struct TrainerContext {
+	/// Ratio of the dataset to be used for training. Rest is used for testing precision.
+	///
+	/// The value is always adjusted to lie between 0 and 1.
+	/// Default value is `0.8`, meaning 4/5 of the dataset is used to train
+	/// and 1/5 is used to test the model.
+	/// The choice is scenariondependant, but commonly used are:
+	/// - 0.8 -> 4/5 train & 1/5 test
+	/// - 0.67 -> 2/3 train & 1/3 test
+	/// - 0.5 -> 1/2 train & 1/2 test
+	pub training_distribution: f32,
+	/// Method of the train and test split
+	///
+	/// By default this is set to `Split::Random`.
+	pub split: Split,
+	/// Random seed, for reproducibility.
+	/// By default set to None, which means random seed.
+	pub rng_seed: Option<u64>,
+	/// Learning rate
+	///
+	/// By default it is set to `0.1`.
+	/// Learning rate *alpha* as generally used in ML / AI slang.
+	/// This is the initial value, adjusted every epoch by `schedule`.
+	pub learning_rate: f64,
+	/// Learning rate schedule
+	///
+	/// By default this is constant learning rate without warm-up.
+	pub schedule: Schedule,
+	/// Theta for the linear regression equations, `theta[0]` is the intercept
+	///
+	/// By default it is empty, meaning all thetas start at 0.0
+	pub theta: Vec<f64>,
+	/// Path to file to save training results
+	///
+	/// By default this is set to `data/weights`.
+	pub outfile: String,
+	/// Format of the saved model
+	///
+	/// By default this is set to `Format::Json`.
+	pub model_format: Format,
+	/// Path to directory to store statistics for the training
+	///
+	/// By default this is set to `stats/`.
+	pub stats_dir: String,
+	/// Temporal Difference limit
+	///
+	/// By default this is set to `0.001`.
+	/// The linear regression will loop until reaching this limit.
+	/// If iteration limit is set, the loop will prioritize that value over this limit.
+	pub temp_diff_limit: f64,
+	/// Iterations limit, counted in epochs
+	///
+	/// By default this is set to `Option::None`.
+	/// If this value is set, then the linear regression loop will
+	/// stop upon reaching this number and ignore `temp_diff_limit`.
+	pub iterations: Option<usize>,
+	/// Theta update rule of the gradient descent
+	///
+	/// By default this is set to `OptimizerKind::GradientDescent`.
+	pub optimizer: OptimizerKind,
+	/// Batching of the gradient descent
+	///
+	/// By default this is set to `Batching::Full`.
+	/// Stochastic batching rarely reaches small `temp_diff_limit` with constant
+	/// learning rate, so it should be combined with `iterations` limit.
+	pub batching: Batching,
+	/// Method used to fit theta
+	///
+	/// By default this is set to `Solver::GradientDescent`.
+	/// Closed-form solvers ignore learning rate and the loop limits,
+	/// coordinate descent uses the limits counting sweeps over the features.
+	pub solver: Solver,
+	/// Reaction to an epoch that increases the cost or makes it non-finite
+	///
+	/// By default this is set to `Divergence::Abort`.
+	pub divergence: Divergence,
+	/// Relative cost increase per epoch still accepted as convergent
+	///
+	/// Full batch plain gradient descent of a smooth loss compares to the cost of the
+	/// previous epoch. Stochastic batching, the other optimizers and the absolute and
+	/// quantile losses don't decrease the cost monotonically, so they compare to the mean
+	/// cost of the accepted epochs instead.
+	///
+	/// By default this is set to `Option::None`, which means only rounding noise (`1e-9`)
+	/// for the monotonic case and ten times above the mean cost (`10`) for the others, noisy
+	/// epochs stay well within that while a diverging cost grows geometrically.
+	pub divergence_tolerance: Option<f64>,
+	/// Scaling of the features for the gradient descent and the regularized solvers
+	///
+	/// By default this is set to `Scaler::MinMax`.
+	pub feature_scaler: Scaler,
+	/// Scaling of the target for the gradient descent
+	///
+	/// By default this is set to `Scaler::None`. Note that `temp_diff_limit`
+	/// applies to theta of the scaled values.
+	pub target_scaler: Scaler,
+	/// Cross-validation run by [`Trainer::cross_validate`]
+	///
+	/// By default this is set to `Option::None`, meaning no cross-validation.
+	pub cross_validation: Option<CrossValidation>,
+	/// Penalty of the coefficients, never applied to the intercept
+	///
+	/// By default this is set to `Penalty::None`, ordinary least squares.
+	pub regularization: Regularization,
+	/// Loss of the residuals minimized by the gradient descent
+	///
+	/// By default this is set to `LossKind::Squared`, least squares.
+	/// The closed-form and coordinate descent solvers fit the squared loss only.
+	pub loss: LossKind,
+	/// Settings of `Solver::Ransac`, seeded by `rng_seed`
+	///
+	/// By default the threshold is the median absolute deviation of the target, 100 trials.
+	pub ransac: Ransac,
+	/// Grid of the regularization path run by [`Trainer::regularization_path`]
+	///
+	/// By default this is set to `Option::None`, meaning `regularization.lambda` is used as given.
+	pub path: Option<PathSettings>,
+}
+
+impl TrainerContext {
+	/// Helper function to decide whether the main linear regression loop should continue
+	///
+	/// The training is done if number of iteration is reached or if not specified
+	/// the temporal difference limit is reached
+	fn is_done(&self, current_iter: usize, temp_diff: &[f64]) -> bool {
+		match self.iterations {
+			Some(limit) => current_iter >= limit,
+			None => temp_diff.iter().all(|d| self.temp_diff_limit >= d.abs()),
+		}
+	}
+	/// Whether the gradient descent should decrease the cost every epoch
+	fn is_monotone(&self) -> bool {
+		self.batching == Batching::Full
+			&& matches!(self.optimizer, OptimizerKind::GradientDescent)
+			&& self.loss.is_smooth()
+	}
+	/// Effective `divergence_tolerance`
+	fn divergence_tolerance(&self) -> f64 {
+		match self.divergence_tolerance {
+			Some(tolerance) => tolerance,
+			None if self.is_monotone() => 1e-9,
+			None => 10.0,
+		}
+	}
+	/// Random seed setter
+	pub fn set_seed(&mut self, seed: u64) {
+		self.rng_seed = Some(seed);
+	}
+	/// Random seed getter
+	///
+	/// If the seed is None, new seed is randomly generated, assigned and returned
+	pub fn get_seed(&mut self) -> u64 {
+		*self
+			.rng_seed
+			.get_or_insert_with(|| thread_rng().gen::<u64>())
+	}
+}
+
+impl Default for TrainerContext {
+	fn default() -> Self {
+		Self {
+			training_distribution: 0.8,
+			split: Split::default(),
+			rng_seed: Option::None,
+			learning_rate: 0.1,
+			schedule: Schedule::default(),
+			theta: Vec::new(),
+			outfile: String::from("data/weights"),
+			model_format: Format::default(),
+			stats_dir: String::from("stats/"),
+			temp_diff_limit: 0.001,
+			iterations: Option::None,
+			optimizer: OptimizerKind::default(),
+			batching: Batching::default(),
+			solver: Solver::default(),
+			divergence: Divergence::default(),
+			divergence_tolerance: Option::None,
+			feature_scaler: Scaler::MinMax,
+			target_scaler: Scaler::None,
+			cross_validation: Option::None,
+			regularization: Regularization::default(),
+			loss: LossKind::default(),
+			ransac: Ransac::default(),
+			path: Option::None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn do_vecs_match<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+		let matching = a.iter().zip(b.iter()).filter(|&(a, b)| a == b).count();
+		matching == a.len() && matching == b.len()
+	}
+
+	#[test]
+	fn rng_seed_get() {
+		let mut ctx = TrainerContext::default();
+		let x = ctx.get_seed();
+		let y = ctx.get_seed();
+		assert_eq!(x, y);
+	}
+
+	#[test]
+	fn dataset_split_length() {
+		let mut trainer = Trainer::load("data/subject_data.csv", Option::None).unwrap();
+		trainer.split_dataset();
+		assert_eq!(trainer.train_set.len(), 20);
+		assert_eq!(trainer.test_set.len(), 4);
+	}
+
+	#[test]
+	fn multivariate_fit() {
+		// y = 1 + 2a + 3b
+		let samples = (0..20)
+			.map(|i| {
+				let (a, b) = ((i % 5) as f64, (i / 5) as f64);
+				Sample::new(vec![a, b], 1.0 + 2.0 * a + 3.0 * b)
+			})
+			.collect();
+		let dataset = Dataset::new(
+			vec![String::from("a"), String::from("b")],
+			String::from("y"),
+			samples,
+		);
+		let ctx = TrainerContext {
+			training_distribution: 1.0,
+			temp_diff_limit: 1e-9,
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::from_dataset(dataset, Some(ctx));
+		trainer.train().unwrap();
+		let model = trainer.model();
+		for (theta, expected) in model.theta.iter().zip([1.0, 2.0, 3.0].iter()) {
+			assert!((theta - expected).abs() < 1e-4, "{:?}", model.theta);
+		}
+		assert!((model.estimate(&[1.0, 1.0]) - 6.0).abs() < 1e-4);
+	}
+
+	#[test]
+	fn mini_batch_per_seed() {
+		let fit = |seed: u64| {
+			let ctx = TrainerContext {
+				rng_seed: Some(seed),
+				batching: Batching::MiniBatch(4),
+				learning_rate: 0.05,
+				iterations: Some(200),
+				..TrainerContext::default()
+			};
+			let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+			trainer.train().unwrap();
+			trainer.model().theta
+		};
+		let theta = fit(7);
+		assert_eq!(theta, fit(7));
+		assert_ne!(theta, fit(8));
+		assert!(theta[1] < 0.0);
+	}
+
+	#[test]
+	fn empty_training_set() {
+		for batching in [Batching::Full, Batching::Stochastic, Batching::MiniBatch(4)] {
+			let ctx = TrainerContext {
+				training_distribution: 0.0,
+				batching,
+				..TrainerContext::default()
+			};
+			let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+			assert!(matches!(trainer.train(), Err(TrainError::Custom(_))));
+		}
+		assert_eq!(Batching::Full.batch_size(0), 1);
+		let ctx = TrainerContext {
+			training_distribution: 0.0,
+			solver: Solver::CoordinateDescent,
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+		assert!(matches!(trainer.train(), Err(TrainError::Custom(_))));
+	}
+
+	#[test]
+	fn divergence() {
+		let fit = |divergence: Divergence| {
+			let ctx = TrainerContext {
+				rng_seed: Some(1),
+				learning_rate: 5.0,
+				divergence,
+				..TrainerContext::default()
+			};
+			let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+			trainer.train().map(|_| trainer)
+		};
+		assert!(matches!(
+			fit(Divergence::Abort),
+			Err(TrainError::Diverged { epoch: 0, .. })
+		));
+		let trainer = fit(Divergence::Backoff).unwrap();
+		assert!(trainer.model().theta.iter().all(|t| t.is_finite()));
+		assert!(trainer.final_learning_rate().unwrap() < 5.0);
+		// Adam steps stay finite, the rising cost is caught by default
+		let ctx = TrainerContext {
+			rng_seed: Some(1),
+			learning_rate: 1e6,
+			optimizer: "adam".parse().unwrap(),
+			iterations: Some(50),
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+		assert!(matches!(trainer.train(), Err(TrainError::Diverged { .. })));
+	}
+
+	#[test]
+	fn dataset_split_per_seed() {
+		let mut trainer_one = Trainer::load("data/subject_data.csv", Option::None).unwrap();
+		let seed = trainer_one.ctx.get_seed();
+		let mut trainer_two = Trainer::load("data/subject_data.csv", Option::None).unwrap();
+		trainer_two.ctx.set_seed(seed);
+		assert_eq!(seed, trainer_two.ctx.get_seed());
+		trainer_one.split_dataset();
+		trainer_two.split_dataset();
+		assert_eq!(
+			trainer_one.train_set.feature_extremes(),
+			trainer_two.train_set.feature_extremes()
+		);
+		assert!(do_vecs_match(
+			&trainer_one.train_set.samples,
+			&trainer_two.train_set.samples
+		));
+		assert!(do_vecs_match(
+			&trainer_one.test_set.samples,
+			&trainer_two.test_set.samples
+		));
+	}
+}
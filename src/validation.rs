@@ -0,0 +1,239 @@
+//! Cross-validation of the trainer
+//!
+//! Single holdout of a small dataset is noisy, e.g. 4 test rows of the 24 in
+//! `subject_data.csv`. Cross-validation fits a model per fold, tests it on the rows
+//! left out and reports mean and standard deviation of the [`Metrics`] across folds.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dataset::Dataset;
+use crate::metrics::Metrics;
+
+/// Partitioning of the dataset into test folds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Folds {
+	/// `k` shuffled folds of nearly equal size
+	K(usize),
+	/// Every sample is a fold of its own, not shuffled so never repeated
+	LeaveOneOut,
+}
+
+impl Default for Folds {
+	fn default() -> Self {
+		Folds::K(5)
+	}
+}
+
+/// Accepts number of folds of at least 2 or `loo`
+impl FromStr for Folds {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"loo" => Ok(Folds::LeaveOneOut),
+			_ => match s.parse::<usize>() {
+				Ok(k) if k >= 2 => Ok(Folds::K(k)),
+				_ => Err(format!("Invalid folds {:?}, expected at least 2 or loo", s)),
+			},
+		}
+	}
+}
+
+impl fmt::Display for Folds {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Folds::K(k) => write!(f, "{}", k),
+			Folds::LeaveOneOut => write!(f, "loo"),
+		}
+	}
+}
+
+/// Cross-validation settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossValidation {
+	pub folds: Folds,
+	/// Number of times the k-fold is run, with a different shuffle each time, at least 1
+	pub repeats: usize,
+	/// Fit the saved model on the full dataset instead of the holdout train set
+	pub refit: bool,
+}
+
+impl Default for CrossValidation {
+	fn default() -> Self {
+		Self {
+			folds: Folds::default(),
+			repeats: 1,
+			refit: false,
+		}
+	}
+}
+
+impl CrossValidation {
+	/// `(train_set, test_set)` of every fold of every repeat
+	///
+	/// Repeat `r` shuffles with `seed + r`, so the same seed gives the same folds.
+	pub fn splits(&self, dataset: &Dataset, seed: u64) -> Vec<(Dataset, Dataset)> {
+		match self.folds {
+			Folds::LeaveOneOut => dataset.folds(dataset.len(), Option::None),
+			Folds::K(k) => (0..self.repeats as u64)
+				.flat_map(|r| dataset.folds(k, Some(seed.wrapping_add(r))))
+				.collect(),
+		}
+	}
+}
+
+/// Test set metrics of every fold
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvReport {
+	pub folds: Vec<Metrics>,
+}
+
+impl CvReport {
+	/// Mean of every measure across folds, in the order of [`Metrics::NAMES`]
+	///
+	/// Folds where the measure isn't defined, e.g. R² of a single sample fold,
+	/// are left out. If it isn't defined for any fold the result is `f64::NAN`.
+	pub fn mean(&self) -> [f64; 10] {
+		let mut mean = [f64::NAN; 10];
+		for (i, m) in mean.iter_mut().enumerate() {
+			let values = self.defined(i);
+			if !values.is_empty() {
+				*m = values.iter().sum::<f64>() / values.len() as f64;
+			}
+		}
+		mean
+	}
+
+	/// Sample standard deviation of every measure across folds, see [`CvReport::mean`]
+	pub fn std(&self) -> [f64; 10] {
+		let mean = self.mean();
+		let mut std = [f64::NAN; 10];
+		for (i, s) in std.iter_mut().enumerate() {
+			let values = self.defined(i);
+			if values.len() > 1 {
+				let sum: f64 = values.iter().map(|v| (v - mean[i]).powi(2)).sum();
+				*s = (sum / (values.len() - 1) as f64).sqrt();
+			}
+		}
+		std
+	}
+
+	/// Name and value pairs, `cv_<metric>_mean` and `cv_<metric>_std`
+	pub fn named(&self) -> Vec<(String, f64)> {
+		let (mean, std) = (self.mean(), self.std());
+		Metrics::NAMES
+			.iter()
+			.enumerate()
+			.flat_map(|(i, name)| {
+				[
+					(format!("cv_{}_mean", name), mean[i]),
+					(format!("cv_{}_std", name), std[i]),
+				]
+			})
+			.collect()
+	}
+
+	fn defined(&self, i: usize) -> Vec<f64> {
+		self.folds
+			.iter()
+			.map(|m| m.values()[i])
+			.filter(|v| !v.is_nan())
+			.collect()
+	}
+}
+
+/// Table of mean and standard deviation of every measure
+impl fmt::Display for CvReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (mean, std) = (self.mean(), self.std());
+		write!(
+			f,
+			"Cross-validation over {} folds\n{:<20}{:>16}{:>16}",
+			self.folds.len(),
+			"metric",
+			"mean",
+			"std"
+		)?;
+		for (i, name) in Metrics::NAMES.iter().enumerate() {
+			write!(f, "\n{:<20}{:>16.4}{:>16.4}", name, mean[i], std[i])?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::dataset::Sample;
+
+	fn dataset(n: usize) -> Dataset {
+		Dataset::new(
+			vec![String::from("x")],
+			String::from("y"),
+			(0..n)
+				.map(|i| Sample::new(vec![i as f64], i as f64))
+				.collect(),
+		)
+	}
+
+	#[test]
+	fn splits() {
+		let dataset = dataset(24);
+		let cv = CrossValidation {
+			folds: Folds::K(5),
+			repeats: 2,
+			refit: false,
+		};
+		let splits = cv.splits(&dataset, 7);
+		assert_eq!(splits.len(), 10);
+		for repeat in splits.chunks(5) {
+			let mut tested: Vec<f64> = repeat
+				.iter()
+				.flat_map(|(train, test)| {
+					assert_eq!(train.len() + test.len(), 24);
+					assert!(test.len() == 4 || test.len() == 5);
+					test.samples.iter().map(|s| s.target)
+				})
+				.collect();
+			tested.sort_by(|a, b| a.total_cmp(b));
+			assert_eq!(tested, (0..24).map(|i| i as f64).collect::<Vec<_>>());
+		}
+		assert_ne!(splits[0].1.samples, splits[5].1.samples);
+		for ((_, a), (_, b)) in splits.iter().zip(cv.splits(&dataset, 7).iter()) {
+			assert_eq!(a.samples, b.samples);
+		}
+
+		let loo = CrossValidation {
+			folds: Folds::LeaveOneOut,
+			..cv
+		};
+		let splits = loo.splits(&dataset, 7);
+		assert_eq!(splits.len(), 24);
+		assert_eq!(splits[3].1.samples, vec![Sample::new(vec![3.0], 3.0)]);
+		assert_eq!("loo".parse(), Ok(Folds::LeaveOneOut));
+		assert_eq!("10".parse(), Ok(Folds::K(10)));
+		assert!("1".parse::<Folds>().is_err());
+	}
+
+	#[test]
+	fn report() {
+		let model =
+			crate::LinearModel::new(vec![String::from("x")], String::from("y"), vec![0.0, 0.5]);
+		let folds: Vec<Metrics> = [dataset(4), dataset(1)]
+			.iter()
+			.map(|set| Metrics::evaluate(&model, set).unwrap())
+			.collect();
+		// Mean absolute errors 0.75 and 0
+		let report = CvReport { folds };
+		assert_eq!(report.mean()[0], 0.375);
+		assert!((report.std()[0] - 0.75 / 2.0_f64.sqrt()).abs() < 1e-12);
+		// R² of the single sample fold is not defined
+		assert_eq!(report.mean()[7], report.folds[0].r2);
+		assert!(report.std()[7].is_nan());
+		assert_eq!(
+			report.named()[1],
+			(String::from("cv_mae_std"), report.std()[0])
+		);
+		assert_eq!(report.to_string().lines().count(), 12);
+	}
+}
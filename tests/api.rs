@@ -0,0 +1,982 @@
+//! Integration tests of the public library API
+use ft_linear_regression::csv::CsvOptions;
+use ft_linear_regression::dataset::{Column, LoadOptions, Sample, Split};
+use ft_linear_regression::domain::Extrapolation;
+use ft_linear_regression::inference::IntervalKind;
+use ft_linear_regression::metrics::Metrics;
+use ft_linear_regression::missing::{Imputation, Invalid, MissingPolicy};
+use ft_linear_regression::model_io::{BinaryError, Format, Metadata};
+use ft_linear_regression::path::{LambdaRule, PathSettings};
+use ft_linear_regression::predictor::{BadRows, BatchOptions, Interval, OutputFormat, Prediction};
+use ft_linear_regression::regularization::{Penalty, Regularization};
+use ft_linear_regression::scaler::Scaler;
+use ft_linear_regression::schedule::Schedule;
+use ft_linear_regression::solver::Solver;
+use ft_linear_regression::validation::{CrossValidation, Folds};
+use ft_linear_regression::{
+	model_io, Dataset, LinearModel, Predictor, TrainError, Trainer, TrainerContext,
+};
+
+fn context(seed: u64) -> TrainerContext {
+	TrainerContext {
+		rng_seed: Some(seed),
+		outfile: std::env::temp_dir()
+			.join(format!("ft_linear_regression_api_{}", seed))
+			.to_string_lossy()
+			.to_string(),
+		..TrainerContext::default()
+	}
+}
+
+#[test]
+fn dataset_load() {
+	let dataset = Dataset::load("data/subject_data.csv").unwrap();
+	assert_eq!(dataset.feature_labels, vec![String::from("km")]);
+	assert_eq!(dataset.target_label, "price");
+	assert_eq!(dataset.len(), 24);
+}
+
+#[test]
+fn train_and_predict() {
+	let ctx = context(42);
+	let outfile = ctx.outfile.clone();
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	trainer.train().unwrap();
+	trainer.test_accuracy();
+	assert!(trainer.train_metrics().unwrap().r2 > 0.5);
+	assert!(trainer.test_metrics().is_some());
+	let model = trainer.model();
+	// Mileage lowers the price
+	assert!(model.theta[1] < 0.0);
+	assert!((model.theta[0] - 8500.0).abs() < 500.0);
+
+	trainer.save_output(None).unwrap();
+	let predictor = Predictor::load(&outfile).unwrap();
+	assert_eq!(predictor.model(), &model);
+	std::fs::remove_file(outfile).unwrap();
+}
+
+#[test]
+fn model_roundtrip() {
+	let model = LinearModel::new(
+		vec![String::from("a"), String::from("b")],
+		String::from("y"),
+		vec![1.5, -0.25, 2.0],
+	);
+	let path = std::env::temp_dir().join("ft_linear_regression_api_roundtrip");
+	let path = path.to_str().unwrap();
+	model_io::save(&model, path).unwrap();
+	assert_eq!(model_io::load(path).unwrap(), model);
+	assert_eq!(model.estimate(&[2.0, 1.0]), 3.0);
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn model_formats() {
+	// Pretrained legacy model shipped with the repository
+	let legacy = Predictor::load("data/weights").unwrap();
+	assert_eq!(legacy.get_labels(), &[String::from("km")]);
+	assert_eq!(
+		legacy.model().theta,
+		vec![8115.670142453336, -0.02221416420207365]
+	);
+	assert_eq!(legacy.metadata(), &Metadata::default());
+
+	let mut ctx = context(43);
+	ctx.model_format = Format::Json;
+	let outfile = ctx.outfile.clone();
+	let mut dataset = Dataset::load("data/subject_data.csv").unwrap();
+	dataset.feature_labels = vec![String::from("distance driven")];
+	let hash = dataset.fingerprint();
+	let mut trainer = Trainer::from_dataset(dataset, Some(ctx));
+	trainer.train().unwrap();
+	trainer.test_accuracy();
+	trainer.save_output(None).unwrap();
+	let saved = model_io::load_saved(&outfile).unwrap();
+	assert_eq!(saved.model, trainer.model());
+	assert_eq!(
+		saved.model.feature_labels,
+		vec![String::from("distance driven")]
+	);
+	assert_eq!(saved.metadata.seed, Some(43));
+	assert_eq!(saved.metadata.dataset_hash, Some(hash));
+	assert_eq!(saved.metadata.scaling.as_ref(), trainer.scaling());
+	let metrics = &saved.metadata.metrics;
+	assert_eq!(metrics.len(), 2 * Metrics::NAMES.len());
+	assert_eq!(
+		metrics[0],
+		(
+			String::from("train_mae"),
+			trainer.train_metrics().unwrap().mae
+		)
+	);
+	assert!(metrics.contains(&(String::from("test_r2"), trainer.test_metrics().unwrap().r2)));
+	assert!(saved
+		.metadata
+		.hyperparameters
+		.contains(&(String::from("solver"), String::from("gd"))));
+
+	std::fs::write(&outfile, "{\"format_version\": 1").unwrap();
+	let err = Predictor::load(&outfile).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	std::fs::remove_file(outfile).unwrap();
+}
+
+#[test]
+fn binary_model() {
+	let mut ctx = context(44);
+	ctx.model_format = Format::Binary;
+	let outfile = ctx.outfile.clone();
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	trainer.train().unwrap();
+	trainer.save_output(None).unwrap();
+	let predictor = Predictor::load(&outfile).unwrap();
+	assert_eq!(predictor.model(), &trainer.model());
+
+	let rejection = |bytes: &[u8]| {
+		std::fs::write(&outfile, bytes).unwrap();
+		let err = Predictor::load(&outfile).unwrap_err();
+		let reason = err
+			.get_ref()
+			.unwrap()
+			.downcast_ref::<BinaryError>()
+			.cloned();
+		(err.kind(), reason.unwrap())
+	};
+	let bytes = std::fs::read(&outfile).unwrap();
+	assert_eq!(
+		rejection(&bytes[..bytes.len() - 3]),
+		(std::io::ErrorKind::UnexpectedEof, BinaryError::Truncated)
+	);
+	let mut corrupted = bytes.clone();
+	*corrupted.last_mut().unwrap() ^= 1;
+	assert!(matches!(
+		rejection(&corrupted),
+		(
+			std::io::ErrorKind::InvalidData,
+			BinaryError::Checksum { .. }
+		)
+	));
+	std::fs::remove_file(outfile).unwrap();
+}
+
+#[test]
+fn batch_prediction() {
+	let predictor = Predictor::from(LinearModel::new(
+		vec![String::from("a"), String::from("b")],
+		String::from("y"),
+		vec![1.0, 2.0, 3.0],
+	));
+	let input = "id;b;a\n\"x;1\";1;2\nz;NA;1\n";
+	let predict = |features: Option<Vec<Column>>, bad_rows: BadRows| {
+		let options = BatchOptions {
+			csv: CsvOptions {
+				delimiter: ';',
+				has_header: true,
+			},
+			features,
+			bad_rows,
+			..BatchOptions::default()
+		};
+		let mut out = Vec::new();
+		predictor
+			.predict_csv(input, &options, &mut out)
+			.map(|report| (String::from_utf8(out).unwrap(), report))
+	};
+	// Columns are matched by the model feature names
+	let (out, report) = predict(None, BadRows::Blank).unwrap();
+	assert_eq!(out, "id;b;a;predicted_y\r\n\"x;1\";1;2;8\r\nz;NA;1;\r\n");
+	assert_eq!((report.rows, report.predicted, report.blank), (2, 1, 1));
+	let (out, report) = predict(Some(vec!["b".into(), "a".into()]), BadRows::Skip).unwrap();
+	assert_eq!(out, "id;b;a;predicted_y\r\n\"x;1\";1;2;9\r\n");
+	assert_eq!(report.skipped, 1);
+	assert!(matches!(
+		predict(None, BadRows::Fail),
+		Err(TrainError::InvalidValue {
+			line: 3,
+			column: 3,
+			..
+		})
+	));
+	assert!(matches!(
+		predict(Some(vec!["c".into(), "a".into()]), BadRows::Fail),
+		Err(TrainError::UnknownColumn { .. })
+	));
+}
+
+#[test]
+fn prediction_formats() {
+	let predictor = Predictor::from(LinearModel::new(
+		vec![String::from("a"), String::from("b,c")],
+		String::from("y"),
+		vec![1.0, 2.0, 3.0],
+	));
+	let mut prediction = predictor.predict(&[0.5, 2.0]);
+	assert_eq!(prediction.estimate, 8.0);
+	assert_eq!(prediction.interval, None);
+	let render = |prediction: &Prediction, format: &str| {
+		predictor.render(prediction, format.parse().unwrap())
+	};
+	assert_eq!(
+		render(&prediction, "text"),
+		"The estimate for 0.5 [a], 2 [b,c] is 8.000 [y]."
+	);
+	assert_eq!(
+		render(&prediction, "json"),
+		"{\n\t\"features\": {\n\t\t\"a\": 0.5,\n\t\t\"b,c\": 2.0\n\t},\n\t\"target\": \"y\",\n\t\"estimate\": 8.0\n}"
+	);
+	assert_eq!(
+		render(&prediction, "csv"),
+		"a,\"b,c\",predicted_y\r\n0.5,2,8"
+	);
+	assert_eq!(render(&prediction, "raw"), "8");
+	prediction.interval = Some(Interval {
+		kind: IntervalKind::Prediction,
+		level: 0.95,
+		lower: 7.0,
+		upper: 9.5,
+	});
+	assert_eq!(render(&prediction, "raw"), "8 7 9.5");
+	assert!(render(&prediction, "text")
+		.ends_with(" is 8.000 [y], 95% prediction interval 7.000 to 9.500."));
+	assert!(render(&prediction, "csv").ends_with(",predicted_y,lower,upper\r\n0.5,2,8,7,9.5"));
+	assert!(render(&prediction, "json").contains("\t\"interval\": \"prediction\",\n\t\"level\": 0.95,\n\t\"lower\": 7.0,\n\t\"upper\": 9.5\n}"));
+	assert!("xml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn prediction_intervals() {
+	let mut ctx = context(47);
+	ctx.solver = Solver::Qr;
+	ctx.training_distribution = 1.0;
+	ctx.model_format = Format::Json;
+	let outfile = ctx.outfile.clone();
+	let dataset = Dataset::load("data/subject_data.csv").unwrap();
+	let km: Vec<f64> = dataset.samples.iter().map(|s| s.features[0]).collect();
+	let mut trainer = Trainer::from_dataset(dataset.clone(), Some(ctx));
+	trainer.train().unwrap();
+	let inference = trainer.inference().unwrap();
+	assert_eq!((inference.n, inference.degrees_of_freedom()), (24, 22));
+	trainer.save_output(None).unwrap();
+	let predictor = Predictor::load(&outfile).unwrap();
+	std::fs::remove_file(outfile).unwrap();
+	assert_eq!(predictor.metadata().inference.as_ref(), Some(inference));
+
+	// Textbook single feature formulas
+	let n = km.len() as f64;
+	let mean = km.iter().sum::<f64>() / n;
+	let sxx: f64 = km.iter().map(|x| (x - mean).powi(2)).sum();
+	let sse: f64 = dataset
+		.samples
+		.iter()
+		.map(|s| (s.target - predictor.model().estimate(&s.features)).powi(2))
+		.sum();
+	let s = (sse / (n - 2.0)).sqrt();
+	// t quantile for 97.5% and 22 degrees of freedom
+	let t = 2.0738730679040147;
+	for x in [50000.0, 100000.0, 250000.0] {
+		let h = 1.0 / n + (x - mean).powi(2) / sxx;
+		for (kind, se) in [
+			(IntervalKind::Confidence, s * h.sqrt()),
+			(IntervalKind::Prediction, s * (1.0 + h).sqrt()),
+		] {
+			let prediction = predictor.predict_interval(&[x], kind, 0.95);
+			let interval = prediction.interval.unwrap();
+			assert!((prediction.estimate - t * se - interval.lower).abs() < 1e-6);
+			assert!((prediction.estimate + t * se - interval.upper).abs() < 1e-6);
+		}
+	}
+
+	// Legacy models don't know the statistics
+	let legacy = Predictor::load("data/weights").unwrap();
+	assert_eq!(
+		legacy
+			.predict_interval(&[1000.0], IntervalKind::Prediction, 0.95)
+			.interval,
+		None
+	);
+}
+
+#[test]
+fn extrapolation() {
+	let mut ctx = context(53);
+	ctx.solver = Solver::Qr;
+	ctx.model_format = Format::Json;
+	let outfile = ctx.outfile.clone();
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	trainer.train().unwrap();
+	trainer.save_output(None).unwrap();
+	let predictor = Predictor::load(&outfile).unwrap();
+	std::fs::remove_file(outfile).unwrap();
+	let domain = predictor.metadata().domain.clone().unwrap();
+	assert_eq!(Some(&domain), trainer.domain());
+	let (min, max) = (domain.features[0].min, domain.features[0].max);
+	assert!(min >= 22899.0 && max <= 240000.0 && min < max);
+
+	let inside = predictor.predict(&[min]);
+	assert!(inside.extrapolated.is_empty());
+	assert!(predictor.warnings(&inside).is_empty());
+	let outside = predictor.predict(&[-5.0]);
+	assert_eq!(outside.extrapolated, vec![0]);
+	assert!(predictor.warnings(&outside)[0].contains("-5 [km]"));
+	assert_eq!(
+		predictor.constrain(&[-5.0], Extrapolation::Warn).unwrap(),
+		vec![-5.0]
+	);
+	assert_eq!(
+		predictor.constrain(&[-5.0], Extrapolation::Clamp).unwrap(),
+		vec![min]
+	);
+	assert!(matches!(
+		predictor.constrain(&[-5.0], Extrapolation::Refuse),
+		Err(TrainError::OutOfDomain { line: None, .. })
+	));
+	assert!(predictor.constrain(&[max], Extrapolation::Refuse).is_ok());
+
+	let input = format!("km\n{}\n-5\n", max);
+	let predict = |extrapolation, bad_rows| {
+		let options = BatchOptions {
+			extrapolation,
+			bad_rows,
+			..BatchOptions::default()
+		};
+		let mut out = Vec::new();
+		predictor
+			.predict_csv(&input, &options, &mut out)
+			.map(|report| (String::from_utf8(out).unwrap(), report))
+	};
+	let (out, report) = predict(Extrapolation::Clamp, BadRows::Fail).unwrap();
+	assert_eq!((report.predicted, report.extrapolated), (2, 1));
+	assert!(out.ends_with(&format!("-5,{}\r\n", inside.estimate)));
+	assert!(report.warnings.is_empty());
+	let (_, report) = predict(Extrapolation::Warn, BadRows::Fail).unwrap();
+	assert_eq!(report.warnings.len(), 1);
+	assert!(report.warnings[0].starts_with("Warning: line 3, key -5 [km]"));
+	let (_, report) = predict(Extrapolation::Refuse, BadRows::Skip).unwrap();
+	assert_eq!((report.predicted, report.skipped), (1, 1));
+	assert!(matches!(
+		predict(Extrapolation::Refuse, BadRows::Fail),
+		Err(TrainError::OutOfDomain { line: Some(3), .. })
+	));
+}
+
+#[test]
+fn cross_validation() {
+	let run = |cv: Option<CrossValidation>| {
+		let mut ctx = context(59);
+		ctx.solver = Solver::Qr;
+		ctx.cross_validation = cv;
+		let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+		let report = cv.map(|_| trainer.cross_validate().unwrap());
+		trainer.train().unwrap();
+		(trainer, report)
+	};
+	let (holdout, _) = run(None);
+	let cv = CrossValidation {
+		folds: Folds::K(5),
+		repeats: 2,
+		refit: false,
+	};
+	let (trainer, report) = run(Some(cv));
+	let report = report.unwrap();
+	assert_eq!(report.folds.len(), 10);
+	assert_eq!(report.folds.iter().map(|m| m.n).sum::<usize>(), 48);
+	// Cross-validation doesn't change the saved model
+	assert_eq!(trainer.model(), holdout.model());
+	let metrics = trainer.metadata().metrics;
+	assert!(metrics.contains(&(String::from("cv_rmse_mean"), report.mean()[2])));
+	assert!(trainer
+		.metadata()
+		.hyperparameters
+		.contains(&(String::from("cv_folds"), String::from("5"))));
+
+	let (trainer, report) = run(Some(CrossValidation {
+		folds: Folds::LeaveOneOut,
+		repeats: 1,
+		refit: true,
+	}));
+	assert_eq!(report.unwrap().folds.len(), 24);
+	assert_eq!(trainer.inference().unwrap().n, 24);
+
+	let mut ctx = context(59);
+	ctx.cross_validation = Some(CrossValidation {
+		folds: Folds::K(25),
+		..cv
+	});
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	assert!(trainer.cross_validate().is_err());
+
+	let mut ctx = context(59);
+	ctx.cross_validation = Some(CrossValidation { repeats: 0, ..cv });
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	assert!(trainer.cross_validate().is_err());
+}
+
+#[test]
+fn split_methods() {
+	let dataset = Dataset::new(
+		vec![String::from("t")],
+		String::from("y"),
+		(0..100)
+			.map(|i| Sample::new(vec![i as f64], ((i * 37) % 100) as f64))
+			.collect(),
+	);
+	let targets = |set: &Dataset| set.samples.iter().map(|s| s.target).collect::<Vec<_>>();
+	for ratio in [0.0, 0.5, 0.75, 0.9, 1.0] {
+		let test_count = (100.0 * (1.0 - ratio)) as usize;
+		for method in [
+			Split::Random,
+			Split::Stratified { bins: 4 },
+			Split::Chronological,
+		] {
+			let (train, test) = dataset.split_with(method, ratio, 3);
+			assert_eq!(test.len(), test_count, "{} {}", method, ratio);
+			assert_eq!(train.len(), 100 - test_count);
+			let (train_again, test_again) = dataset.split_with(method, ratio, 3);
+			assert_eq!(train.samples, train_again.samples);
+			assert_eq!(test.samples, test_again.samples);
+		}
+	}
+	// Every sample is as likely to be tested, early rows aren't preferred
+	let first_half: usize = (0..200)
+		.map(|seed| {
+			let (_, test) = dataset.split(0.9, seed);
+			test.samples.iter().filter(|s| s.features[0] < 50.0).count()
+		})
+		.sum();
+	assert!((800..1200).contains(&first_half), "{}", first_half);
+
+	let (_, test) = dataset.split_with(Split::Stratified { bins: 4 }, 0.8, 3);
+	let mut per_bin = [0; 4];
+	for y in targets(&test) {
+		per_bin[y as usize / 25] += 1;
+	}
+	assert_eq!(per_bin, [5, 5, 5, 5]);
+
+	let (train, test) = dataset.split_with(Split::Chronological, 0.8, 3);
+	assert_eq!(train.samples, dataset.samples[..80]);
+	assert_eq!(test.samples, dataset.samples[80..]);
+
+	assert_eq!("stratified".parse(), Ok(Split::Stratified { bins: 5 }));
+	assert_eq!(
+		"stratified:bins=3".parse::<Split>().unwrap().to_string(),
+		"stratified:bins=3"
+	);
+	assert!("stratified:bins=0".parse::<Split>().is_err());
+	assert!("chronological:bins=2".parse::<Split>().is_err());
+}
+
+#[test]
+fn multivariate_load() {
+	let features = [Column::from(1), Column::from(5), Column::from(6)];
+	let dataset = Dataset::load_columns("data/diamonds.csv", &features, &Column::from(7)).unwrap();
+	assert_eq!(dataset.feature_count(), 3);
+	assert_eq!(dataset.len(), 53940);
+	assert_eq!(dataset.samples[0].features, vec![0.23, 61.5, 55.0]);
+	assert_eq!(dataset.samples[0].target, 326.0);
+}
+
+#[test]
+fn column_selection() {
+	let dataset = Dataset::load_columns(
+		"data/salary_data.csv",
+		&[Column::from("YearsExperience")],
+		&Column::from("Salary"),
+	)
+	.unwrap();
+	assert_eq!(
+		dataset.feature_labels,
+		vec![String::from("YearsExperience")]
+	);
+	assert_eq!(
+		dataset.samples[0],
+		ft_linear_regression::dataset::Sample::new(vec![1.1], 39343.0)
+	);
+
+	// By index, in reversed roles
+	let dataset =
+		Dataset::load_columns("data/salary_data.csv", &[Column::from("1")], &0.into()).unwrap();
+	assert_eq!(dataset.target_label, "YearsExperience");
+
+	match Dataset::load_columns("data/salary_data.csv", &[Column::from("Age")], &1.into()) {
+		Err(TrainError::UnknownColumn { column, available }) => {
+			assert_eq!(column, "\"Age\"");
+			assert_eq!(available, vec!["YearsExperience", "Salary"]);
+		}
+		other => panic!("Expected UnknownColumn error, got {:?}", other),
+	}
+	assert!(Dataset::load_columns("data/salary_data.csv", &[2.into()], &1.into()).is_err());
+}
+
+#[test]
+fn quoted_header() {
+	let dataset = Dataset::load("data/diamonds_carat_price.csv").unwrap();
+	assert_eq!(dataset.feature_labels, vec![String::from("carat")]);
+	assert_eq!(dataset.target_label, "price");
+
+	let dataset =
+		Dataset::load_columns("data/diamonds.csv", &["carat".into()], &"price".into()).unwrap();
+	assert_eq!(dataset.len(), 53940);
+}
+
+#[test]
+fn invalid_value_position() {
+	// Column "cut" holds text
+	match Dataset::load_columns("data/diamonds.csv", &["cut".into()], &"price".into()) {
+		Err(TrainError::InvalidValue {
+			line,
+			column,
+			value,
+			reason,
+		}) => {
+			assert_eq!((line, column), (2, 10));
+			assert_eq!(value, "Ideal");
+			assert_eq!(reason, Invalid::NonNumeric);
+		}
+		other => panic!("Expected InvalidValue error, got {:?}", other),
+	}
+}
+
+#[test]
+fn missing_value_policies() {
+	let path = std::env::temp_dir().join("ft_linear_regression_api_missing.csv");
+	let path = path.to_str().unwrap();
+	std::fs::write(path, "x,y\n1,10\nNA,20\n3,\"\"\ninf,40\n5,50\nabc,60\n").unwrap();
+	let load = |missing| {
+		let options = LoadOptions {
+			missing,
+			..LoadOptions::default()
+		};
+		Dataset::load_with_report(path, &options)
+	};
+
+	match load(MissingPolicy::Fail) {
+		Err(TrainError::InvalidValue { line, reason, .. }) => {
+			assert_eq!((line, reason), (3, Invalid::Missing))
+		}
+		other => panic!("Expected InvalidValue error, got {:?}", other),
+	}
+
+	let (dataset, report) = load(MissingPolicy::Skip).unwrap();
+	assert_eq!(dataset.len(), 2);
+	assert_eq!(report.rows, 6);
+	assert_eq!(
+		(
+			report.dropped.missing,
+			report.dropped.non_numeric,
+			report.dropped.non_finite
+		),
+		(2, 1, 1)
+	);
+
+	// Row with missing target is dropped, features get the median of 1, 5
+	let (dataset, report) = load(MissingPolicy::Impute(Imputation::Median)).unwrap();
+	assert_eq!(dataset.len(), 5);
+	assert_eq!(report.dropped.total(), 1);
+	assert_eq!(report.imputed.total(), 3);
+	let xs: Vec<f64> = dataset.samples.iter().map(|s| s.features[0]).collect();
+	assert_eq!(xs, vec![1.0, 3.0, 3.0, 5.0, 3.0]);
+	std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn closed_form_solvers() {
+	let thetas: Vec<Vec<f64>> = [Solver::NormalEquation, Solver::Qr]
+		.iter()
+		.map(|solver| {
+			let ctx = TrainerContext {
+				training_distribution: 1.0,
+				solver: *solver,
+				..TrainerContext::default()
+			};
+			let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+			trainer.train().unwrap();
+			trainer.model().theta
+		})
+		.collect();
+	// Reference least squares fit of the subject data
+	for theta in thetas.iter() {
+		assert!((theta[0] - 8499.599649933216).abs() < 1e-6, "{:?}", theta);
+		assert!((theta[1] + 0.0214489635917023).abs() < 1e-12, "{:?}", theta);
+	}
+}
+
+#[test]
+fn gradient_descent_matches_least_squares() {
+	let fit = |ctx: TrainerContext| {
+		let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+		trainer.train().unwrap();
+		trainer.model().theta
+	};
+	let exact = fit(TrainerContext {
+		training_distribution: 1.0,
+		solver: Solver::Qr,
+		..TrainerContext::default()
+	});
+	for feature_scaler in [Scaler::MinMax, Scaler::ZScore, Scaler::Robust] {
+		for target_scaler in [Scaler::None, Scaler::ZScore] {
+			let theta = fit(TrainerContext {
+				training_distribution: 1.0,
+				temp_diff_limit: 1e-12,
+				feature_scaler,
+				target_scaler,
+				..TrainerContext::default()
+			});
+			// Intercept is far from zero since mileage doesn't start at zero
+			assert!(
+				(theta[0] - exact[0]).abs() < 1e-3,
+				"{} {}: {:?}",
+				feature_scaler,
+				target_scaler,
+				theta
+			);
+			assert!((theta[1] - exact[1]).abs() < 1e-8, "{:?}", theta);
+		}
+	}
+}
+
+#[test]
+fn regularized_solvers() {
+	let fit = |solver: Solver, regularization: Regularization| {
+		let ctx = TrainerContext {
+			training_distribution: 1.0,
+			temp_diff_limit: 1e-10,
+			solver,
+			regularization,
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx))?;
+		trainer.train()?;
+		Ok::<_, TrainError>(trainer.model().theta)
+	};
+	let ols = fit(Solver::Qr, Regularization::default()).unwrap();
+	let ridge = Regularization {
+		penalty: Penalty::Ridge,
+		lambda: 0.1,
+		..Regularization::default()
+	};
+	let thetas: Vec<Vec<f64>> = [
+		Solver::NormalEquation,
+		Solver::Qr,
+		Solver::CoordinateDescent,
+		Solver::GradientDescent,
+	]
+	.iter()
+	.map(|solver| fit(*solver, ridge).unwrap())
+	.collect();
+	for theta in thetas.iter() {
+		assert!((theta[0] - thetas[0][0]).abs() < 1e-3, "{:?}", thetas);
+		assert!((theta[1] - thetas[0][1]).abs() < 1e-8, "{:?}", thetas);
+	}
+	// Slope is shrunk towards zero
+	assert!(ols[1] < thetas[0][1] && thetas[0][1] < 0.0, "{:?}", thetas);
+
+	let lasso = Regularization {
+		penalty: Penalty::Lasso,
+		lambda: 100.0,
+		..Regularization::default()
+	};
+	let cd = fit(Solver::CoordinateDescent, lasso).unwrap();
+	let gd = fit(Solver::GradientDescent, lasso).unwrap();
+	assert!((cd[1] - gd[1]).abs() < 1e-8, "{:?} {:?}", cd, gd);
+	assert!(matches!(fit(Solver::Qr, lasso), Err(TrainError::Custom(_))));
+
+	// Large enough penalty zeroes the slope, the intercept is left to fit the mean
+	let dataset = Dataset::load("data/subject_data.csv").unwrap();
+	let mean = dataset.samples.iter().map(|s| s.target).sum::<f64>() / dataset.len() as f64;
+	for penalty in [Penalty::Lasso, Penalty::ElasticNet] {
+		let theta = fit(
+			Solver::CoordinateDescent,
+			Regularization {
+				penalty,
+				lambda: 1e6,
+				..Regularization::default()
+			},
+		)
+		.unwrap();
+		assert_eq!(theta[1], 0.0);
+		assert!((theta[0] - mean).abs() < 1e-6, "{:?}", theta);
+	}
+}
+
+#[test]
+fn regularization_path() {
+	let mut ctx = context(61);
+	ctx.solver = Solver::CoordinateDescent;
+	ctx.temp_diff_limit = 1e-9;
+	ctx.path = Some(PathSettings {
+		count: 20,
+		..PathSettings::default()
+	});
+	let outfile = ctx.outfile.clone();
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	assert!(matches!(
+		trainer.regularization_path(),
+		Err(TrainError::Custom(_))
+	));
+
+	let mut ctx = context(61);
+	ctx.regularization = Regularization {
+		penalty: Penalty::Lasso,
+		..Regularization::default()
+	};
+	ctx.path = Some(PathSettings {
+		count: 20,
+		rule: LambdaRule::Min,
+		..PathSettings::default()
+	});
+	ctx.cross_validation = Some(CrossValidation {
+		folds: Folds::K(4),
+		..CrossValidation::default()
+	});
+	let mut trainer = Trainer::load("data/subject_data.csv", Some(ctx)).unwrap();
+	let path = trainer.regularization_path().unwrap();
+	assert_eq!(path.lambdas.len(), 20);
+	assert!(path.lambdas.windows(2).all(|w| w[0] > w[1]));
+	assert_eq!((path.cv_mse.len(), path.coefficients.len()), (20, 20));
+	// Largest lambda zeroes the slope, the smallest is close to least squares
+	assert_eq!(path.coefficients[0][1], 0.0);
+	assert!(
+		(path.coefficients[19][1] + 0.0214).abs() < 1e-3,
+		"{:?}",
+		path.coefficients[19]
+	);
+	assert!(path.lambda_1se >= path.lambda_min);
+	let best = path.cv_mse.iter().cloned().fold(f64::INFINITY, f64::min);
+	let min_index = path
+		.lambdas
+		.iter()
+		.position(|l| *l == path.lambda_min)
+		.unwrap();
+	assert_eq!(path.cv_mse[min_index], best);
+	assert_eq!(trainer.context().regularization.lambda, path.lambda_min);
+
+	trainer.train().unwrap();
+	trainer.save_output(None).unwrap();
+	let saved = model_io::load_saved(&outfile).unwrap();
+	assert_eq!(saved.metadata.path.as_ref(), Some(&path));
+	assert!(saved
+		.metadata
+		.hyperparameters
+		.contains(&(String::from("lambda"), path.lambda_min.to_string())));
+	std::fs::remove_file(outfile).unwrap();
+}
+
+#[test]
+fn robust_losses() {
+	let mut dataset = Dataset::load("data/subject_data.csv").unwrap();
+	let clean = dataset.clone();
+	// Listing with a typo in the price
+	dataset.samples.push(Sample::new(vec![150000.0], 60000.0));
+	let fit = |dataset: &Dataset, loss: &str, solver: Solver| {
+		let ctx = TrainerContext {
+			training_distribution: 1.0,
+			target_scaler: Scaler::ZScore,
+			iterations: Some(20000),
+			schedule: Schedule {
+				decay: "inverse-time".parse().unwrap(),
+				warmup: 0,
+			},
+			loss: loss.parse().unwrap(),
+			solver,
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::from_dataset(dataset.clone(), Some(ctx));
+		trainer.train()?;
+		assert!(trainer.inference().is_none() || loss == "squared");
+		Ok::<_, TrainError>(trainer.model())
+	};
+	let slope = |dataset: &Dataset, loss: &str| {
+		fit(dataset, loss, Solver::GradientDescent).unwrap().theta[1]
+	};
+	let shift = |loss: &str| (slope(&dataset, loss) - slope(&clean, loss)).abs();
+	// The outlier drags the least squares slope, far less the robust ones
+	for loss in ["absolute", "huber", "log-cosh"] {
+		assert!(
+			shift(loss) * 3.0 < shift("squared"),
+			"{}: {} {}",
+			loss,
+			shift(loss),
+			shift("squared")
+		);
+	}
+
+	// Quantile lines leave about tau of the prices below them
+	for tau in [0.1, 0.5, 0.9] {
+		let model = fit(
+			&clean,
+			&format!("quantile:tau={}", tau),
+			Solver::GradientDescent,
+		)
+		.unwrap();
+		let below = clean
+			.samples
+			.iter()
+			.filter(|s| s.target < model.estimate(&s.features))
+			.count() as f64
+			/ clean.len() as f64;
+		assert!((below - tau).abs() <= 0.1, "{}: {}", tau, below);
+	}
+	assert!(matches!(
+		fit(&clean, "huber", Solver::Qr),
+		Err(TrainError::Custom(_))
+	));
+}
+
+#[test]
+fn robust_solvers() {
+	let clean = Dataset::load("data/subject_data.csv").unwrap();
+	let mut dataset = clean.clone();
+	// Mileage entered in meters
+	dataset
+		.samples
+		.push(Sample::new(vec![82_000_000.0], 6200.0));
+	dataset
+		.samples
+		.push(Sample::new(vec![61_500_000.0], 7000.0));
+	let fit = |dataset: &Dataset, solver: Solver| {
+		let mut ctx = context(48);
+		ctx.training_distribution = 1.0;
+		ctx.solver = solver;
+		ctx.ransac.threshold = Some(1500.0);
+		let mut trainer = Trainer::from_dataset(dataset.clone(), Some(ctx));
+		trainer.train().map(|_| trainer)
+	};
+	let exact = fit(&clean, Solver::Qr).unwrap().model().theta;
+	let skewed = fit(&dataset, Solver::Qr).unwrap().model().theta;
+	assert!(skewed[1].abs() * 10.0 < exact[1].abs(), "{:?}", skewed);
+
+	let ransac = fit(&dataset, Solver::Ransac).unwrap();
+	let inliers = ransac.inliers().unwrap();
+	assert_eq!(inliers.len(), 26);
+	for (sample, inlier) in ransac.train_set().samples.iter().zip(inliers.iter()) {
+		if sample.features[0] > 1e6 {
+			assert!(!inlier);
+		}
+	}
+	let theta = ransac.model().theta;
+	assert!(
+		(theta[1] - exact[1]).abs() < 0.01,
+		"{:?} {:?}",
+		theta,
+		exact
+	);
+	// Intervals come from the inliers
+	assert!(ransac.inference().is_some());
+	assert!(ransac
+		.metadata()
+		.hyperparameters
+		.contains(&(String::from("ransac_threshold"), String::from("1500"))));
+	// Same seed, same inliers
+	assert_eq!(
+		fit(&dataset, Solver::Ransac).unwrap().inliers(),
+		Some(inliers)
+	);
+
+	let theil_sen = fit(&dataset, Solver::TheilSen).unwrap();
+	assert!(theil_sen.inliers().is_none() && theil_sen.inference().is_none());
+	let theta = theil_sen.model().theta;
+	assert!(
+		(theta[1] - exact[1]).abs() < 0.01,
+		"{:?} {:?}",
+		theta,
+		exact
+	);
+
+	let two_features = Dataset::load_columns(
+		"data/diamonds_carat_price.csv",
+		&[Column::from(0), Column::from(0)],
+		&Column::from(1),
+	)
+	.unwrap();
+	assert!(matches!(
+		fit(&two_features, Solver::TheilSen),
+		Err(TrainError::Custom(_))
+	));
+}
+
+#[test]
+fn sample_weights() {
+	let path = std::env::temp_dir().join("ft_linear_regression_api_weights.csv");
+	let path = path.to_str().unwrap();
+	let load = |content: &str| {
+		std::fs::write(path, content).unwrap();
+		let options = LoadOptions {
+			weight: Some(Column::from("w")),
+			..LoadOptions::default()
+		};
+		Dataset::load_with_report(path, &options)
+	};
+	match load("x,y,w\n1,10,1\n2,20,-0.5\n") {
+		Err(TrainError::NegativeWeight { line, column, .. }) => {
+			assert_eq!((line, column), (3, 6))
+		}
+		other => panic!("Expected NegativeWeight error, got {:?}", other),
+	}
+	let (dataset, report) = load("x,y,w\n1,2,1\n2,3,0\n3,7,3\n4,8,1\n5,9,2\n6,14,1\n").unwrap();
+	std::fs::remove_file(path).unwrap();
+	assert_eq!((dataset.len(), report.zero_weight), (5, 1));
+	assert_eq!(dataset.weight_label.as_deref(), Some("w"));
+	assert!(report
+		.to_string()
+		.ends_with("ignored 1 rows of zero weight"));
+
+	// Integer weights fit as repeated rows
+	let mut repeated = dataset.clone();
+	repeated.weight_label = None;
+	repeated.samples = dataset
+		.samples
+		.iter()
+		.flat_map(|s| vec![Sample::new(s.features.clone(), s.target); s.weight as usize])
+		.collect();
+	let fit = |dataset: &Dataset, solver: Solver| {
+		let ctx = TrainerContext {
+			training_distribution: 1.0,
+			temp_diff_limit: 1e-12,
+			solver,
+			..TrainerContext::default()
+		};
+		let mut trainer = Trainer::from_dataset(dataset.clone(), Some(ctx));
+		trainer.train().unwrap();
+		trainer.test_accuracy();
+		trainer
+	};
+	let exact = fit(&repeated, Solver::Qr);
+	for solver in [
+		Solver::NormalEquation,
+		Solver::Qr,
+		Solver::CoordinateDescent,
+		Solver::GradientDescent,
+		Solver::TheilSen,
+	] {
+		let weighted = fit(&dataset, solver).model().theta;
+		let repeated = fit(&repeated, solver).model().theta;
+		for (w, r) in weighted.iter().zip(repeated.iter()) {
+			assert!(
+				(w - r).abs() < 1e-6,
+				"{}: {:?} {:?}",
+				solver,
+				weighted,
+				repeated
+			);
+		}
+	}
+	let weighted = fit(&dataset, Solver::Qr);
+	let (a, b) = (
+		weighted.train_metrics().unwrap(),
+		exact.train_metrics().unwrap(),
+	);
+	assert_eq!((a.n, b.n), (5, 8));
+	for (name, (x, y)) in Metrics::NAMES
+		.iter()
+		.zip(a.values().iter().zip(b.values().iter()))
+	{
+		if *name != "adjusted_r2" {
+			assert!((x - y).abs() < 1e-9, "{}: {} {}", name, x, y);
+		}
+	}
+	assert!(weighted
+		.metadata()
+		.hyperparameters
+		.contains(&(String::from("weight"), String::from("w"))));
+}